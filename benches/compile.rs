@@ -0,0 +1,59 @@
+//! Compares the interpreter with compiled code on the day 9 BOOST program in sensor boost mode, a
+//! long run where compiling pays off, and on the day 2 noun/verb search, ten thousand runs of a few
+//! dozen instructions each, where setting up each run costs about as much as decoding saves.
+//!
+//! Run with `cargo bench`.
+
+use advent_of_code_2019::{Compiled, Program};
+use std::fs::read_to_string;
+use std::time::{Duration, Instant};
+
+fn main() -> advent_of_code_2019::Result<()> {
+    let day02 = read_to_string("input/day02.txt")?.parse::<Program>()?;
+    let compiled = Compiled::new(&day02);
+    compare("day 2 search",
+        || {
+            for (noun, verb) in pairs() {
+                let _ = day02.fork().change_values(noun, verb).run_to_halt();
+            }
+        },
+        || {
+            for (noun, verb) in pairs() {
+                let _ = compiled.runner(day02.fork().change_values(noun, verb)).run_to_halt();
+            }
+        },
+    );
+
+    let mut day09 = read_to_string("input/day09.txt")?.parse::<Program>()?;
+    day09.push_input(2);
+    let compiled = Compiled::new(&day09);
+    compare("day 9 boost",
+        || { day09.fork().run_to_halt().unwrap(); },
+        || { compiled.runner(day09.fork()).run_to_halt().unwrap(); },
+    );
+
+    Ok(())
+}
+
+fn pairs() -> impl Iterator<Item = (i64, i64)> {
+    (0..=99).flat_map(|noun| (0..=99).map(move |verb| (noun, verb)))
+}
+
+fn compare<I: FnMut(), C: FnMut()>(name: &str, interpreted: I, compiled: C) {
+    let interpreted = time(interpreted);
+    let compiled = time(compiled);
+    println!("{:<14} interpreted {:>10.2?}  compiled {:>10.2?}  speedup {:.2}x",
+        name, interpreted, compiled, interpreted.as_secs_f64() / compiled.as_secs_f64());
+}
+
+/// The fastest of several runs
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    (0..10)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
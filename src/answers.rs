@@ -0,0 +1,161 @@
+use crate::*;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::read_to_string;
+use std::str::FromStr;
+
+/// Where the known answers are kept, next to `input/`
+pub const DEFAULT_PATH: &str = "answers.txt";
+
+/// Known-correct answers by day and part, read from lines like
+///
+/// ```text
+/// # Comments and blank lines are skipped
+/// 1.1 3415076
+/// 1.2 5119745
+/// 11.2
+/// | # #
+/// |#
+/// ```
+///
+/// Each line is `DAY.PART ANSWER`. An answer that is a number is read as one, and anything else as
+/// text. A `DAY.PART` alone on its line is a picture, made of the lines after it that start with
+/// `|`: each of them is a row, after the `|`, ending in a newline.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Answers(BTreeMap<(u8, u8), Answer>);
+
+impl Answers {
+    pub fn load(path: &str) -> Result<Self> {
+        let answers = read_to_string(path).map_err(|e| InputError::new(format!("{}: {}", path, e)))?;
+        answers.parse()
+    }
+
+    pub fn get(&self, day: u8, part: u8) -> Option<&Answer> {
+        self.0.get(&(day, part))
+    }
+
+    pub fn insert(&mut self, day: u8, part: u8, answer: Answer) -> Option<Answer> {
+        self.0.insert((day, part), answer)
+    }
+
+    /// Check an answer, or the lack of one, against the known answer
+    pub fn check(&self, day: u8, part: u8, found: Option<&Answer>) -> Check {
+        Check::new(self.get(day, part), found)
+    }
+
+    /// Add a known answer read from a file, which can only give each part one
+    fn add(&mut self, day: u8, part: u8, answer: Answer) -> Result<()> {
+        if answer == Answer::Text(String::new()) {
+            return ioerr!(format!("Day {}, part {} has no answer", day, part));
+        }
+        if self.insert(day, part, answer).is_some() {
+            return ioerr!(format!("Day {}, part {} answered twice", day, part));
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Answers {
+    type Err = BoxError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut answers = Answers::default();
+        // The part whose picture is being read, and its rows so far
+        let mut picture: Option<((u8, u8), String)> = None;
+
+        for (n, line) in s.lines().enumerate() {
+            let invalid = || InputError::new(format!("line {}: {}", n + 1, line));
+
+            if let Some(row) = line.trim_start().strip_prefix('|') {
+                let (_, rows) = picture.as_mut().ok_or_else(invalid)?;
+                rows.push_str(row);
+                rows.push('\n');
+                continue;
+            }
+            if let Some(((day, part), rows)) = picture.take() {
+                answers.add(day, part, Answer::Text(rows))?;
+            }
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, answer) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let (day, part) = key.split_once('.')
+                .and_then(|(day, part)| Some((day.parse::<u8>().ok()?, part.parse::<u8>().ok()?)))
+                .filter(|(day, part)| (1..=25).contains(day) && (1..=2).contains(part))
+                .ok_or_else(invalid)?;
+
+            match answer.trim() {
+                "" => picture = Some(((day, part), String::new())),
+                answer => answers.add(day, part, match answer.parse::<i64>() {
+                    Ok(number) => Answer::Number(number),
+                    Err(_) => Answer::from(answer),
+                })?,
+            }
+        }
+        if let Some(((day, part), rows)) = picture {
+            answers.add(day, part, Answer::Text(rows))?;
+        }
+
+        Ok(answers)
+    }
+}
+
+/// How an answer compares with the known one
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Check {
+    Pass,
+    /// Wrong, or no answer where one is known
+    Fail,
+    /// There's no known answer to compare with
+    Missing,
+}
+
+impl Check {
+    pub fn new(expected: Option<&Answer>, found: Option<&Answer>) -> Self {
+        match (expected, found) {
+            (None, _) => Check::Missing,
+            (Some(expected), Some(found)) if expected == found => Check::Pass,
+            _ => Check::Fail,
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Check::Pass => write!(f, "pass"),
+            Check::Fail => write!(f, "fail"),
+            Check::Missing => write!(f, "missing"),
+        }
+    }
+}
+
+#[test]
+fn parse_answers() -> Result<()> {
+    let answers = "
+        # Checked on the site
+        1.1 3415076
+        1.2 RJLFBUCU
+
+        11.2
+        | # #
+        |#
+    ".parse::<Answers>()?;
+
+    assert_eq!(answers.get(1, 1), Some(&Answer::Number(3415076)));
+    assert_eq!(answers.get(1, 2), Some(&Answer::from("RJLFBUCU")));
+    assert_eq!(answers.get(11, 2), Some(&Answer::from(" # #\n#\n")));
+    assert_eq!(answers.check(1, 1, Some(&Answer::Number(3415076))), Check::Pass);
+    assert_eq!(answers.check(1, 2, Some(&Answer::Number(5119745))), Check::Fail);
+    assert_eq!(answers.check(11, 2, None), Check::Fail);
+    assert_eq!(answers.check(2, 1, Some(&Answer::Number(1))), Check::Missing);
+
+    for invalid in &["1 1", "26.1 1", "1.3 1", "|#", "1.1\n1.2 5", "1.1 1\n1.1 2"] {
+        assert!(invalid.parse::<Answers>().is_err(), "{}", invalid);
+    }
+
+    Ok(())
+}
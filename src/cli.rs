@@ -1,33 +1,339 @@
-use clap::{Arg, App};
+use advent_of_code_2019::input::{self, Inputs, Source};
+use advent_of_code_2019::{boxerr, ioerr, Budget, InputError, Program};
+use crate::report::Format;
+use clap::{Arg, App, AppSettings, ArgMatches, SubCommand};
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+/// The longest timeout, about 30 years
+const MAX_SECONDS: f64 = 1e9;
 
 pub fn app() -> App<'static, 'static> {
     App::new("Advent of Code 2019")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(Arg::with_name("day")
-            .help("Which day to execute")
+            .help("Which days to execute, like 3 or 5-12")
             .short("d")
             .long("day")
             .takes_value(true)
             .multiple(true)
             .value_delimiter(",")
-            .required(true)
-            .validator(valid_day))
+            .required_unless("all")
+            .validator(|s| day_range(&s).map(|_| ())))
+        .arg(Arg::with_name("format")
+            .help("How to write the answers")
+            .long("format")
+            .takes_value(true)
+            .possible_values(&Format::NAMES)
+            .default_value("text"))
+        .arg(Arg::with_name("check")
+            .help("Check the answers against the known ones, failing if any are wrong")
+            .short("c")
+            .long("check"))
+        .arg(Arg::with_name("answers")
+            .help("Path to the known answers [default: answers.txt]")
+            .long("answers")
+            .takes_value(true)
+            .requires("check"))
+        .arg(Arg::with_name("all")
+            .help("Execute every day")
+            .short("a")
+            .long("all")
+            .conflicts_with("day"))
         .arg(Arg::with_name("input")
-            .help("Path to puzzle input file")
-            .requires("day")
+            .help("Path to puzzle input file, or - for standard input. Give it as DAY=PATH or DAY.PART=PATH \
+                to read it for one day, or one part of a day.")
+            .short("i")
+            .long("input")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(|s| input_mapping(&s).map(|_| ())))
+        .arg(input_dir_arg())
+        .arg(Arg::with_name("timeout")
+            .help("Give up on a day that takes longer than this many seconds")
+            .long("timeout")
+            .takes_value(true)
+            .validator(|s| seconds(&s).map(|_| ())))
+        .subcommand(intcode_subcommand("disasm")
+            .about("Print a labelled listing of an Intcode program"))
+        .subcommand(intcode_subcommand("analyze")
+            .about("Report what an Intcode program reads, writes and jumps to, without running it")
+            .arg(Arg::with_name("dot")
+                .help("Print the control flow graph as Graphviz DOT instead")
+                .long("dot")))
+        .subcommand(intcode_subcommand("debug")
+            .about("Step through an Intcode program interactively"))
+        .subcommand(intcode_subcommand("trace")
+            .about("Print every instruction an Intcode program executes")
+            .arg(feed_arg())
+            .args(&budget_args())
+            .arg(Arg::with_name("json")
+                .help("Write the trace as JSON lines")
+                .long("json")))
+        .subcommand(intcode_subcommand("record")
+            .about("Run an Intcode program on the terminal, recording its input and output to a tape")
+            .arg(feed_arg())
+            .arg(tape_arg()))
+        .subcommand(intcode_subcommand("replay")
+            .about("Replay a tape to an Intcode program, reporting where it first diverges from it")
+            .arg(tape_arg()))
+        .subcommand(intcode_subcommand("profile")
+            .about("Count the instructions an Intcode program executes, by opcode and address")
+            .arg(feed_arg())
+            .args(&budget_args()))
+}
+
+/// A subcommand that loads an Intcode program from a day's puzzle input, or any other file
+fn intcode_subcommand(name: &'static str) -> App<'static, 'static> {
+    SubCommand::with_name(name)
+        .arg(Arg::with_name("day")
+            .help("Which day's puzzle input to load")
+            .short("d")
+            .long("day")
+            .takes_value(true)
+            .required_unless("input")
+            .validator(|s| day(&s).map(|_| ())))
+        .arg(Arg::with_name("input")
+            .help("Path to Intcode program file, or - for standard input")
             .short("i")
             .long("input")
-            .multiple(false)
             .takes_value(true))
+        .arg(input_dir_arg())
+}
+
+fn input_dir_arg() -> Arg<'static, 'static> {
+    Arg::with_name("input-dir")
+        .help("Directory to find each day's puzzle input in, as dayNN.txt [default: input]")
+        .long("input-dir")
+        .takes_value(true)
+        .env(input::DIR_VAR)
+}
+
+fn feed_arg() -> Arg<'static, 'static> {
+    Arg::with_name("feed")
+        .help("Comma separated values to queue as the program's input")
+        .short("f")
+        .long("feed")
+        .takes_value(true)
+        .allow_hyphen_values(true)
+}
+
+fn tape_arg() -> Arg<'static, 'static> {
+    Arg::with_name("tape")
+        .help("Path to the tape file")
+        .short("t")
+        .long("tape")
+        .takes_value(true)
+        .required(true)
 }
 
-fn valid_day(s: String) -> Result<(), String> {
-    if let Ok(u) = s.parse::<u8>() {
-        if u <= 25 {
-            Ok(())
-        } else {
-            Err("Must be less than or equal to 25!".to_string())
+/// Limits on how long a program may run before it is given up on
+fn budget_args() -> [Arg<'static, 'static>; 2] {
+    [
+        Arg::with_name("max-steps")
+            .help("Stop after executing this many instructions")
+            .long("max-steps")
+            .takes_value(true)
+            .validator(|s| s.parse::<usize>().map(|_| ()).map_err(|e| e.to_string())),
+        Arg::with_name("timeout")
+            .help("Stop after this many seconds")
+            .long("timeout")
+            .takes_value(true)
+            .validator(|s| seconds(&s).map(|_| ())),
+    ]
+}
+
+/// The budget given by the `budget_args` of a subcommand. `--max-steps` isn't part of it: it's
+/// the program's `step_limit`, set by `program`
+pub fn budget(matches: &ArgMatches) -> advent_of_code_2019::Result<Budget> {
+    let mut budget = Budget::unlimited();
+
+    if let Some(seconds) = matches.value_of("timeout") {
+        budget = budget.timeout(self::seconds(seconds).map_err(InputError::new)?);
+    }
+
+    Ok(budget)
+}
+
+/// Load the Intcode program named by the arguments of an `intcode_subcommand`
+pub fn program(matches: &ArgMatches) -> advent_of_code_2019::Result<Program> {
+    let inputs = input_dir(matches);
+    let day = matches.value_of("day").map(self::day).transpose().map_err(InputError::new)?;
+    let input = match (matches.value_of("input"), day) {
+        (Some(input), Some(day)) => inputs.read(day, &Source::from(input))?,
+        (Some(input), None) => inputs.read_source(&Source::from(input))?,
+        (None, Some(day)) => inputs.read(day, &inputs.source(day, 1))?,
+        (None, None) => return ioerr!("No Intcode program given"),
+    };
+
+    let mut program = input.parse::<Program>()?;
+
+    if let Some(steps) = matches.value_of("max-steps") {
+        program.step_limit = Some(steps.parse()?);
+    }
+
+    if let Some(values) = matches.value_of("feed") {
+        for value in values.split(',') {
+            program.push_input(value.trim().parse::<i64>()?);
         }
-    } else {
-        Err("Must be a number less than or equal to 25!".to_string())
     }
+
+    Ok(program)
+}
+
+/// The days named by `--day`, in the order given, or every day for `--all`
+pub fn days(matches: &ArgMatches) -> advent_of_code_2019::Result<Vec<u8>> {
+    if matches.is_present("all") {
+        return Ok((1..=25).collect());
+    }
+
+    let mut days = Vec::new();
+    for value in matches.values_of("day").into_iter().flatten() {
+        days.extend(day_range(value)?);
+    }
+
+    Ok(days)
+}
+
+/// Where each of `days` reads its puzzle input from
+pub fn inputs(matches: &ArgMatches, days: &[u8]) -> advent_of_code_2019::Result<Inputs> {
+    let mut inputs = input_dir(matches);
+
+    for value in matches.values_of("input").into_iter().flatten() {
+        match input_mapping(value)? {
+            (Some((day, part)), source) => inputs.set(day, part, source),
+            (None, source) => match days {
+                [day] => inputs.set(*day, None, source),
+                _ => return ioerr!(format!("Give --input {} as DAY=PATH to say which day reads it", value)),
+            },
+        }
+    }
+
+    Ok(inputs)
+}
+
+fn input_dir(matches: &ArgMatches) -> Inputs {
+    match matches.value_of("input-dir") {
+        Some(dir) => Inputs::in_dir(dir),
+        None => Inputs::default(),
+    }
+}
+
+/// The day an `--input` is for, and the part if it's only for one
+type InputKey = (u8, Option<u8>);
+
+/// An `--input` value: a source for the one day being run, or `DAY=PATH` or `DAY.PART=PATH`
+fn input_mapping(s: &str) -> Result<(Option<InputKey>, Source), String> {
+    let (key, path) = match s.split_once('=') {
+        Some((key, path)) => (key, path),
+        None => return Ok((None, Source::from(s))),
+    };
+
+    let (day, part) = match key.split_once('.') {
+        Some((day, part)) => (day, Some(part)),
+        None => (key, None),
+    };
+    let day = match day_range(day)? {
+        days if days.start() == days.end() => *days.start(),
+        _ => return Err(format!("{:?} is more than one day!", day)),
+    };
+    let part = match part.map(str::trim) {
+        None => None,
+        Some("1") => Some(1),
+        Some("2") => Some(2),
+        Some(part) => return Err(format!("{:?} is not part 1 or 2!", part)),
+    };
+
+    Ok((Some((day, part)), Source::from(path)))
+}
+
+/// How long each day may take, if there's a limit
+pub fn timeout(matches: &ArgMatches) -> advent_of_code_2019::Result<Option<Duration>> {
+    Ok(match matches.value_of("timeout") {
+        Some(seconds) => Some(self::seconds(seconds).map_err(InputError::new)?),
+        None => None,
+    })
+}
+
+/// A timeout in seconds, like `2.5`. It has to be short enough to add to the current time.
+fn seconds(s: &str) -> Result<Duration, String> {
+    match s.trim().parse::<f64>() {
+        Ok(seconds) if (0.0..=MAX_SECONDS).contains(&seconds) => Ok(Duration::from_secs_f64(seconds)),
+        _ => Err(format!("{:?} is not a number of seconds from 0 to {}!", s, MAX_SECONDS)),
+    }
+}
+
+pub fn format(matches: &ArgMatches) -> Format {
+    matches.value_of("format").and_then(Format::from_name).unwrap_or(Format::Text)
+}
+
+/// A day from 1 to 25
+fn day(s: &str) -> Result<u8, String> {
+    match s.trim().parse::<u8>() {
+        Ok(day) if (1..=25).contains(&day) => Ok(day),
+        _ => Err(format!("{:?} is not a day from 1 to 25!", s)),
+    }
+}
+
+/// A day, or an inclusive range of days like `5-12`
+fn day_range(s: &str) -> Result<RangeInclusive<u8>, String> {
+    let (first, last) = match s.split_once('-') {
+        Some((first, last)) => (first, last),
+        None => (s, s),
+    };
+
+    match (day(first)?, day(last)?) {
+        (first, last) if first <= last => Ok(first..=last),
+        _ => Err(format!("{:?} runs backwards!", s)),
+    }
+}
+
+#[test]
+fn day_ranges() {
+    assert_eq!(day_range("7"), Ok(7..=7));
+    assert_eq!(day_range("5-12"), Ok(5..=12));
+    assert!(day_range("12-5").is_err());
+    assert!(day_range("0").is_err());
+    assert!(day_range("20-26").is_err());
+
+    let matches = app().get_matches_from(vec!["aoc", "-d", "3,5-7", "-d", "1"]);
+    assert_eq!(days(&matches).unwrap(), vec![3, 5, 6, 7, 1]);
+    let matches = app().get_matches_from(vec!["aoc", "--all"]);
+    assert_eq!(days(&matches).unwrap().len(), 25);
+
+    // Subcommands load one day's program
+    assert!(app().get_matches_from_safe(vec!["aoc", "disasm", "--day", "0"]).is_err());
+    assert!(app().get_matches_from_safe(vec!["aoc", "disasm", "--day", "5-7"]).is_err());
+    assert!(app().get_matches_from_safe(vec!["aoc", "disasm", "--day", "25"]).is_ok());
+}
+
+#[test]
+fn input_mappings() {
+    assert_eq!(input_mapping("-"), Ok((None, Source::Stdin)));
+    assert_eq!(input_mapping("2=x.txt"), Ok((Some((2, None)), Source::from("x.txt"))));
+    assert_eq!(input_mapping("3.2=-"), Ok((Some((3, Some(2))), Source::Stdin)));
+    assert!(input_mapping("3.3=x.txt").is_err());
+    assert!(input_mapping("1-2=x.txt").is_err());
+
+    let matches = app().get_matches_from(vec!["aoc", "-d", "1,2", "--input-dir", "dir", "-i", "2=x.txt", "-i", "1.1=-"]);
+    let mapped = inputs(&matches, &days(&matches).unwrap()).unwrap();
+    assert_eq!(mapped.source(1, 1), Source::Stdin);
+    assert_eq!(mapped.source(1, 2), Source::from("dir/day01.txt"));
+    assert_eq!(mapped.source(2, 2), Source::from("x.txt"));
+
+    let matches = app().get_matches_from(vec!["aoc", "-d", "1,2", "-i", "x.txt"]);
+    assert!(inputs(&matches, &days(&matches).unwrap()).is_err());
+}
+
+#[test]
+fn timeouts() {
+    assert_eq!(seconds("2.5"), Ok(Duration::from_millis(2500)));
+    assert_eq!(seconds("0"), Ok(Duration::from_secs(0)));
+    for s in &["-1", "inf", "NaN", "1e300", "soon"] {
+        assert!(seconds(s).is_err(), "{}", s);
+    }
+
+    let matches = app().get_matches_from_safe(vec!["aoc", "--all", "--timeout", "inf"]);
+    assert!(matches.is_err());
 }
@@ -1,25 +1,32 @@
-use advent_of_code_2019::*;
-use std::fs::read_to_string;
+use crate::*;
 
-pub fn main(input: Option<&str>) -> Result<()> {
-    let input = read_to_string(input.unwrap_or("input/day01.txt"))?;
-    let module_masses: Vec<isize> = input.lines()
-        .filter_map(|line| line.parse::<isize>().ok())
-        .collect();
+pub struct Day01;
 
-    let fuel: isize = module_masses.iter()
-        .filter_map(|mass| calc_fuel(*mass))
-        .sum();
+impl Solver for Day01 {
+    /// Module masses
+    type Input = Vec<isize>;
 
-    answer!(1, 1, &fuel);
+    fn parse(&self, input: &str) -> Result<Self::Input> {
+        Ok(input.lines()
+            .filter_map(|line| line.parse::<isize>().ok())
+            .collect())
+    }
+
+    fn part1(&self, module_masses: &Self::Input) -> Result<Answer> {
+        let fuel: isize = module_masses.iter()
+            .filter_map(|mass| calc_fuel(*mass))
+            .sum();
 
-    let total_fuel: isize = module_masses.iter()
-        .map(|mass| calc_total_fuel(*mass))
-        .sum();
+        Ok(fuel.into())
+    }
 
-    answer!(1, 2, &total_fuel);
+    fn part2(&self, module_masses: &Self::Input) -> Result<Answer> {
+        let total_fuel: isize = module_masses.iter()
+            .map(|mass| calc_total_fuel(*mass))
+            .sum();
 
-    Ok(())
+        Ok(total_fuel.into())
+    }
 }
 
 fn calc_fuel(mass: isize) -> Option<isize> {
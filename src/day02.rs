@@ -1,26 +1,27 @@
-use advent_of_code_2019::*;
-use std::fs::read_to_string;
+use crate::*;
 
-pub fn main(input: Option<&str>) -> Result<()> {
-    let input = read_to_string(input.unwrap_or("input/day02.txt"))?;
-    let mut program = Program::from(input);
-    let program2 = program.clone();
-    
-    program.run();
+pub struct Day02;
 
-    answer!(2, 1, program.first());
+impl Solver for Day02 {
+    type Input = Program;
 
-    for x in 0..=99 {
-        for y in 0..=99 {
-            let mut program = program2.clone().change_values(x, y);
-            program.run();
-            if program.first() == 19690720 {
-                answer!(2, 2, format!("{}{}", x, y));
-                break;
-            }
-        }       
+    fn parse(&self, input: &str) -> Result<Self::Input> {
+        Ok(input.parse()?)
     }
 
-    Ok(())
-}
+    fn part1(&self, program: &Program) -> Result<Answer> {
+        let mut program = program.clone();
+        program.run_to_halt()?;
+
+        Ok(program.first().into())
+    }
 
+    fn part2(&self, program: &Program) -> Result<Answer> {
+        let (noun, verb) = match program.find_inputs(&[1, 2], &[0..=99, 0..=99], 19690720).as_deref() {
+            Some([noun, verb]) => (*noun, *verb),
+            _ => return ioerr!("No noun and verb produce 19690720"),
+        };
+
+        Ok((100 * noun + verb).into())
+    }
+}
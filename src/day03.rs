@@ -1,26 +1,38 @@
-use advent_of_code_2019::*;
+use crate::*;
 use std::collections::HashMap;
 use std::ops::{Add, Sub};
 use std::convert::TryFrom;
 use std::fmt;
-use std::fs::read_to_string;
 use std::iter::FromIterator;
 
-pub fn main(input: Option<&str>) -> Result<()> {
-    let input = read_to_string(input.unwrap_or("input/day03.txt"))?;
+pub struct Day03;
 
-    let tracks = input.lines()
-        .filter_map(|line| WireTrack::try_from(line).ok())
-        .collect::<Vec<_>>();
+/// The two wires' tracks
+pub struct Wires([WireTrack; 2]);
 
-    assert_eq!(tracks.len(), 2, "Looking for 2 Wire Tracks!");
+impl Solver for Day03 {
+    type Input = Wires;
 
-    let min_dist = tracks[0].closest_intersection_distance(&tracks[1]);
-    answer!(03, 1, min_dist);
+    fn parse(&self, input: &str) -> Result<Self::Input> {
+        let tracks = input.lines()
+            .filter_map(|line| WireTrack::try_from(line).ok())
+            .collect::<Vec<_>>();
 
-    let min_steps = tracks[0].minimum_steps_to_intersect(&tracks[1]).expect("No intersections!");
-    answer!(03, 2, min_steps);
-    Ok(())
+        match <[WireTrack; 2]>::try_from(tracks) {
+            Ok(tracks) => Ok(Wires(tracks)),
+            Err(tracks) => ioerr!(format!("Looking for 2 Wire Tracks, found {}!", tracks.len())),
+        }
+    }
+
+    fn part1(&self, Wires(tracks): &Self::Input) -> Result<Answer> {
+        Ok(tracks[0].closest_intersection_distance(&tracks[1]).into())
+    }
+
+    fn part2(&self, Wires(tracks): &Self::Input) -> Result<Answer> {
+        let min_steps = tracks[0].minimum_steps_to_intersect(&tracks[1])
+            .ok_or_else(|| InputError::new("No intersections!"))?;
+        Ok(min_steps.into())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -62,8 +74,8 @@ impl WireTrack {
 
     fn minimum_steps_to_intersect(&self, other: &Self) -> Option<usize> {
         self.intersections(other)
-                .iter()
-                .map(|(intersection, _len)| {
+                .keys()
+                .map(|intersection| {
                     self.steps_to_point(intersection).expect("Point doesn't exist!") +
                     other.steps_to_point(intersection).expect("Point doesn't exist!")
                 })
@@ -91,7 +103,7 @@ impl WireTrack {
         if self.vec.is_empty() {
             Point::default()
         } else {
-            self.vec.get(self.vec.len() - 1)
+            self.vec.last()
                 .expect("Couldn't get Point from WireTrack")
                 .end()
         }
@@ -185,7 +197,7 @@ impl fmt::Display for WireTrack {
                 }
 
                 if x >= right {
-                    write!(f, "\n")?;
+                    writeln!(f)?;
                 }
 
             }
@@ -198,11 +210,11 @@ impl fmt::Display for WireTrack {
 #[test]
 fn vector_points() {
     use std::iter::once;
-    let vector1 = Vector::new(Point::new(0,0), Turn::new(Direction::Right, 5));
+    let vector1 = Vector::new(Point { x: 0, y: 0 }, Turn::new(Direction::Right, 5));
     let vector2 = Vector::new(vector1.end(), Turn::new(Direction::Down, 5));
 
     let wire_track = once(vector1).chain(once(vector2)).collect::<WireTrack>();
-    assert_eq!(wire_track.steps_to_point(&Point::new(5,5)).unwrap(), 10);
+    assert_eq!(wire_track.steps_to_point(&Point { x: 5, y: 5 }).unwrap(), 10);
 }
 
 #[test]
@@ -212,8 +224,8 @@ fn steps_to_point() -> Result<()> {
 
     dbg!(track1.points());
 
-    let steps_to_point1 = track1.steps_to_point(&Point::new(6, -5)).unwrap();
-    let steps_to_point2 = track2.steps_to_point(&Point::new(6, -5)).unwrap();
+    let steps_to_point1 = track1.steps_to_point(&Point { x: 6, y: -5 }).unwrap();
+    let steps_to_point2 = track2.steps_to_point(&Point { x: 6, y: -5 }).unwrap();
 
     assert_eq!(steps_to_point1, 15);
     assert_eq!(steps_to_point2, 15);
@@ -322,14 +334,14 @@ impl From<Vector> for WireTrack {
 
         let mut points = vector.points().peekable();
         while let Some(point) = points.next() {
-            if let None = points.peek() {
+            if points.peek().is_none() {
                 wire_track.map.insert(point, Plot::Turn);
             } else {
                 wire_track.map.insert(point, plot);
             }
         }
 
-        wire_track.vec.push(vector.clone());
+        wire_track.vec.push(vector);
 
         wire_track
     }
@@ -342,10 +354,6 @@ struct Point {
 }
 
 impl Point {
-    fn new(x: i16, y: i16) -> Self {
-        Point { x, y }
-    }
-
     fn turn(&self, turn: Turn) -> Self {
         match turn.dir {
             Direction::Left => Point {
@@ -391,6 +399,8 @@ impl Sub for Point {
 
 
 /// ```rust
+/// # struct Point;
+/// # struct Turn;
 /// struct Vector {
 ///     start: Point,
 ///     turn: Turn,
@@ -500,7 +510,7 @@ impl FromIterator<Vector> for WireTrack {
 
 #[test]
 fn points_iter() {
-    let vector = Vector::new(Point::new(0,0), Turn::new(Direction::Right, 5));
+    let vector = Vector::new(Point { x: 0, y: 0 }, Turn::new(Direction::Right, 5));
     assert_eq!(vector.points().count(), 5);
     for point in vector.points() {
         dbg!(point);
@@ -513,25 +523,25 @@ fn vector_end() {
         Point::default(),
         Turn::new(Direction::Right, 5),
     );
-    assert_eq!(vector.end(), Point::new(5, 0));
+    assert_eq!(vector.end(), Point { x: 5, y: 0 });
 
     let vector = Vector::new(
         Point::default(),
         Turn::new(Direction::Left, 5),
     );
-    assert_eq!(vector.end(), Point::new(-5, 0));
+    assert_eq!(vector.end(), Point { x: -5, y: 0 });
 
     let vector = Vector::new(
         Point::default(),
         Turn::new(Direction::Up, 5),
     );
-    assert_eq!(vector.end(), Point::new(0, -5));
+    assert_eq!(vector.end(), Point { x: 0, y: -5 });
 
     let vector = Vector::new(
         Point::default(),
         Turn::new(Direction::Down, 5),
     );
-    assert_eq!(vector.end(), Point::new(0, 5));
+    assert_eq!(vector.end(), Point { x: 0, y: 5 });
 }
 
 
@@ -541,7 +551,6 @@ enum Plot {
     Horiz,
     Vert,
     Turn,
-    Intersect,
 }
 
 impl From<Direction> for Plot {
@@ -558,7 +567,6 @@ impl fmt::Display for Plot {
         write!(f, "{}",  match self {
             Plot::Horiz => '-',
             Plot::Vert => '|',
-            Plot::Intersect => 'X',
             Plot::Turn => '+',
             Plot::Start => 'O',
         })
@@ -1,24 +1,30 @@
-use advent_of_code_2019::*;
+use crate::*;
 use std::collections::HashMap;
-use std::fs::read_to_string;
 use std::ops::RangeInclusive;
 
-pub fn main(input: Option<&str>) -> Result<()> {
-    let input = read_to_string(input.unwrap_or("input/day04.txt"))?;
-    // Puzzle input
-    let range = range_from_str(&input)?;
+pub struct Day04;
 
-    let count = range.clone()
-        .filter(|i| Digits::from(*i).is_possible_pwd_part1())
-        .count();
-    answer!(04, 1, count);
+impl Solver for Day04 {
+    /// Puzzle input
+    type Input = RangeInclusive<i32>;
 
-    let count2 = range
-        .filter(|i| Digits::from(*i).is_possible_pwd_part2())
-        .count();
-    answer!(04, 2, count2);
+    fn parse(&self, input: &str) -> Result<Self::Input> {
+        range_from_str(input)
+    }
+
+    fn part1(&self, range: &Self::Input) -> Result<Answer> {
+        let count = range.clone()
+            .filter(|i| Digits::from(*i).is_possible_pwd_part1())
+            .count();
+        Ok(count.into())
+    }
 
-    Ok(())
+    fn part2(&self, range: &Self::Input) -> Result<Answer> {
+        let count2 = range.clone()
+            .filter(|i| Digits::from(*i).is_possible_pwd_part2())
+            .count();
+        Ok(count2.into())
+    }
 }
 
 fn range_from_str(s: &str) -> Result<RangeInclusive<i32>> {
@@ -91,10 +97,9 @@ impl Digits {
                     .skip(start)
                     .take(len)
                     .all(|d| d == &digit)
+                    && len > *counter.entry(digit).or_insert(0)
                 {
-                    if len > *counter.entry(digit).or_insert(0) {
-                        counter.insert(digit, len);
-                    }
+                    counter.insert(digit, len);
                 }
             }
         }
@@ -141,7 +146,7 @@ impl From<i32> for Digits {
     fn from(i: i32) -> Self {
         let digits = i.to_string()
             .chars()
-            .map(|c| Digit::from(c))
+            .map(Digit::from)
             .collect();
 
         Digits {
@@ -184,5 +189,5 @@ impl From<char> for Digit {
 
 #[test]
 fn from_i32() {
-    let _i = Digits::from(0123456789);
+    let _i = Digits::from(123456789);
 }
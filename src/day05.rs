@@ -1,9 +1,36 @@
-use advent_of_code_2019::*;
-use std::fs::read_to_string;
+use crate::*;
 
-pub fn main(input: Option<&str>) -> Result<()> {
-    let input = read_to_string(input.unwrap_or("input/day05.txt"))?;
+pub struct Day05;
 
-    answer!(05, 1, 42);
+impl Solver for Day05 {
+    type Input = Program;
+
+    fn parse(&self, input: &str) -> Result<Self::Input> {
+        Ok(input.parse()?)
+    }
+
+    fn part1(&self, program: &Program) -> Result<Answer> {
+        Ok(diagnostic(program.clone(), 1)?.into())
+    }
+
+    fn part2(&self, program: &Program) -> Result<Answer> {
+        Ok(diagnostic(program.clone(), 5)?.into())
+    }
+}
+
+/// Run the diagnostic program with a system ID, returning the diagnostic code it outputs last
+fn diagnostic(mut program: Program, system_id: i64) -> Result<i64> {
+    program.push_input(system_id);
+    program.run_to_halt()?
+        .last()
+        .copied()
+        .ok_or_else(|| InputError::new("No diagnostic code!").into())
+}
+
+#[test]
+fn compare_to_eight() -> Result<()> {
+    let program = "3,9,8,9,10,9,4,9,99,-1,8".parse::<Program>()?;
+    assert_eq!(diagnostic(program.clone(), 8)?, 1);
+    assert_eq!(diagnostic(program, 7)?, 0);
     Ok(())
 }
@@ -1,9 +1,90 @@
-use advent_of_code_2019::*;
-use std::fs::read_to_string;
+use crate::*;
 
-pub fn main(input: Option<&str>) -> Result<()> {
-    let _input = read_to_string(input.unwrap_or("input/day07.txt"))?;
+pub struct Day07;
 
-    // answer!(07, 1, 42);
+impl Solver for Day07 {
+    type Input = Program;
+
+    fn parse(&self, input: &str) -> Result<Self::Input> {
+        Ok(input.parse()?)
+    }
+
+    fn part1(&self, program: &Program) -> Result<Answer> {
+        Ok(max_signal(program, [0, 1, 2, 3, 4], false)?.into())
+    }
+
+    fn part2(&self, program: &Program) -> Result<Answer> {
+        Ok(max_signal(program, [5, 6, 7, 8, 9], true)?.into())
+    }
+}
+
+/// The highest signal sent to the thrusters by any ordering of the phase settings
+fn max_signal(program: &Program, phases: [i64; 5], feedback: bool) -> Result<i64> {
+    let mut max = None;
+    for order in permutations(&phases) {
+        let signal = thruster_signal(program, &order, feedback)?;
+        max = max.max(Some(signal));
+    }
+
+    max.ok_or_else(|| InputError::new("No phase settings!").into())
+}
+
+/// Run a chain of amplifiers, one per phase setting, each feeding the next, and the last one
+/// feeding the first if there is feedback. The first amplifier's first signal is 0.
+fn thruster_signal(program: &Program, phases: &[i64], feedback: bool) -> Result<i64> {
+    let mut pipeline = Pipeline::new();
+    let amplifiers = phases.iter()
+        .map(|phase| {
+            let mut amplifier = program.fork();
+            amplifier.push_input(*phase);
+            pipeline.add(amplifier)
+        })
+        .collect::<Vec<usize>>();
+
+    for pair in amplifiers.windows(2) {
+        pipeline.connect(pair[0], pair[1]);
+    }
+    if feedback {
+        pipeline.connect(amplifiers[amplifiers.len() - 1], amplifiers[0]);
+    }
+    pipeline.push_input(amplifiers[0], 0);
+
+    // The last amplifier's signal is the one that goes to the thrusters
+    let sink = amplifiers[amplifiers.len() - 1];
+    pipeline.run()?[sink].ok_or_else(|| InputError::new("No thruster signal!").into())
+}
+
+fn permutations(values: &[i64]) -> Vec<Vec<i64>> {
+    if values.is_empty() {
+        return vec![Vec::new()];
+    }
+
+    let mut all = Vec::new();
+    for (i, first) in values.iter().enumerate() {
+        let mut rest = values.to_vec();
+        rest.remove(i);
+        for mut permutation in permutations(&rest) {
+            permutation.insert(0, *first);
+            all.push(permutation);
+        }
+    }
+
+    all
+}
+
+#[test]
+fn chain() -> Result<()> {
+    let program = "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0".parse::<Program>()?;
+    assert_eq!(thruster_signal(&program, &[4, 3, 2, 1, 0], false)?, 43210);
+    assert_eq!(max_signal(&program, [0, 1, 2, 3, 4], false)?, 43210);
+    Ok(())
+}
+
+#[test]
+fn feedback_loop() -> Result<()> {
+    let program = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5"
+        .parse::<Program>()?;
+    assert_eq!(thruster_signal(&program, &[9, 8, 7, 6, 5], true)?, 139629729);
+    assert_eq!(max_signal(&program, [5, 6, 7, 8, 9], true)?, 139629729);
     Ok(())
 }
@@ -1,9 +1,35 @@
-use advent_of_code_2019::*;
-use std::fs::read_to_string;
+use crate::*;
 
-pub fn main(input: Option<&str>) -> Result<()> {
-    let _input = read_to_string(input.unwrap_or("input/day09.txt"))?;
+pub struct Day09;
 
-    // answer!(09, 1, 42);
+impl Solver for Day09 {
+    type Input = Program;
+
+    fn parse(&self, input: &str) -> Result<Self::Input> {
+        Ok(input.parse()?)
+    }
+
+    fn part1(&self, program: &Program) -> Result<Answer> {
+        Ok(boost(program.clone(), 1)?.into())
+    }
+
+    fn part2(&self, program: &Program) -> Result<Answer> {
+        Ok(boost(program.clone(), 2)?.into())
+    }
+}
+
+/// Run the BOOST program in the given mode, returning the keycode it outputs
+fn boost(mut program: Program, mode: i64) -> Result<i64> {
+    program.push_input(mode);
+    program.run_to_halt()?
+        .last()
+        .copied()
+        .ok_or_else(|| InputError::new("No BOOST keycode!").into())
+}
+
+#[test]
+fn large_number() -> Result<()> {
+    let program = "104,1125899906842624,99".parse::<Program>()?;
+    assert_eq!(boost(program, 1)?, 1125899906842624);
     Ok(())
 }
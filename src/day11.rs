@@ -1,9 +1,99 @@
-use advent_of_code_2019::*;
-use std::fs::read_to_string;
+use crate::*;
+use std::collections::HashMap;
 
-pub fn main(input: Option<&str>) -> Result<()> {
-    let _input = read_to_string(input.unwrap_or("input/day11.txt"))?;
+pub struct Day11;
 
-    // answer!(11, 1, 42);
-    Ok(())
+impl Solver for Day11 {
+    type Input = Program;
+
+    fn parse(&self, input: &str) -> Result<Self::Input> {
+        Ok(input.parse()?)
+    }
+
+    fn part1(&self, program: &Program) -> Result<Answer> {
+        Ok(paint(program.clone(), false)?.panels.len().into())
+    }
+
+    /// The registration identifier, as painted
+    fn part2(&self, program: &Program) -> Result<Answer> {
+        Ok(paint(program.clone(), true)?.to_string().into())
+    }
+}
+
+/// Run the painting robot over a black hull, or one with a single white panel to start on
+fn paint(mut program: Program, start_on_white: bool) -> Result<Robot> {
+    let mut robot = Robot::new();
+    if start_on_white {
+        robot.panels.insert((0, 0), true);
+    }
+
+    program.run_device(&mut robot)?;
+    Ok(robot)
+}
+
+/// The hull painting robot: its camera sees the colour of the panel it's on, and it takes a colour
+/// to paint and then a direction to turn before moving forward a panel
+#[derive(Debug, Clone)]
+struct Robot {
+    position: (i64, i64),
+    /// A unit step, with y increasing downwards
+    facing: (i64, i64),
+    /// Panels painted at least once, and whether they're white now
+    panels: HashMap<(i64, i64), bool>,
+    /// Whether the next output is a turn rather than a colour
+    turning: bool,
+}
+
+impl Robot {
+    fn new() -> Self {
+        Robot { position: (0, 0), facing: (0, -1), panels: HashMap::new(), turning: false }
+    }
+}
+
+impl Device for Robot {
+    fn on_input(&mut self) -> Option<i64> {
+        Some(self.panels.get(&self.position).copied().unwrap_or(false) as i64)
+    }
+
+    fn on_output(&mut self, value: i64) {
+        if self.turning {
+            let (x, y) = self.facing;
+            self.facing = if value == 0 { (y, -x) } else { (-y, x) };
+            self.position = (self.position.0 + self.facing.0, self.position.1 + self.facing.1);
+        } else {
+            self.panels.insert(self.position, value == 1);
+        }
+        self.turning = !self.turning;
+    }
+}
+
+impl std::fmt::Display for Robot {
+    /// The white panels as `#`s
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let white = self.panels.iter().filter(|(_, white)| **white).map(|(panel, _)| *panel).collect::<Vec<_>>();
+        let (min_x, max_x) = (white.iter().map(|p| p.0).min().unwrap_or(0), white.iter().map(|p| p.0).max().unwrap_or(0));
+        let (min_y, max_y) = (white.iter().map(|p| p.1).min().unwrap_or(0), white.iter().map(|p| p.1).max().unwrap_or(0));
+
+        for y in min_y..=max_y {
+            let row = (min_x..=max_x)
+                .map(|x| if self.panels.get(&(x, y)) == Some(&true) { '#' } else { ' ' })
+                .collect::<String>();
+            writeln!(f, "{}", row.trim_end())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn robot() {
+    // The example: each pair is a colour to paint and a way to turn
+    let mut robot = Robot::new();
+    for value in &[1, 0, 0, 0, 1, 0, 1, 0, 0, 1, 1, 0, 1, 0] {
+        robot.on_output(*value);
+    }
+
+    assert_eq!(robot.panels.len(), 6);
+    assert_eq!(robot.position, (0, -1));
+    assert_eq!(robot.on_input(), Some(0));
 }
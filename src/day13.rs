@@ -1,9 +1,77 @@
-use advent_of_code_2019::*;
-use std::fs::read_to_string;
+use crate::*;
+use std::collections::HashMap;
 
-pub fn main(input: Option<&str>) -> Result<()> {
-    let _input = read_to_string(input.unwrap_or("input/day13.txt"))?;
+const BLOCK: i64 = 2;
+const PADDLE: i64 = 3;
+const BALL: i64 = 4;
 
-    // answer!(13, 1, 42);
-    Ok(())
+pub struct Day13;
+
+impl Solver for Day13 {
+    type Input = Program;
+
+    fn parse(&self, input: &str) -> Result<Self::Input> {
+        Ok(input.parse()?)
+    }
+
+    fn part1(&self, program: &Program) -> Result<Answer> {
+        let mut arcade = Arcade::default();
+        program.clone().run_device(&mut arcade)?;
+        Ok(arcade.tiles.values().filter(|tile| **tile == BLOCK).count().into())
+    }
+
+    fn part2(&self, program: &Program) -> Result<Answer> {
+        // Insert quarters to play for free
+        let mut program = program.clone();
+        program.data[0] = 2;
+        let mut arcade = Arcade::default();
+        program.run_device(&mut arcade)?;
+        Ok(arcade.score.into())
+    }
+}
+
+/// The arcade cabinet: a screen drawn three values at a time, and a joystick that keeps the paddle
+/// under the ball
+#[derive(Debug, Clone, Default)]
+struct Arcade {
+    tiles: HashMap<(i64, i64), i64>,
+    score: i64,
+    /// The values of the instruction being drawn so far
+    pending: Vec<i64>,
+    ball: i64,
+    paddle: i64,
+}
+
+impl Device for Arcade {
+    fn on_input(&mut self) -> Option<i64> {
+        Some((self.ball - self.paddle).signum())
+    }
+
+    fn on_output(&mut self, value: i64) {
+        self.pending.push(value);
+        if let [x, y, tile] = self.pending[..] {
+            self.pending.clear();
+            match (x, y, tile) {
+                (-1, 0, score) => self.score = score,
+                (x, _, BALL) => self.ball = x,
+                (x, _, PADDLE) => self.paddle = x,
+                _ => (),
+            }
+            if x >= 0 {
+                self.tiles.insert((x, y), tile);
+            }
+        }
+    }
+}
+
+#[test]
+fn arcade() {
+    let mut arcade = Arcade::default();
+    for value in &[1, 2, 3, 6, 5, 4, -1, 0, 12345] {
+        arcade.on_output(*value);
+    }
+
+    assert_eq!(arcade.tiles, vec![((1, 2), PADDLE), ((6, 5), BALL)].into_iter().collect());
+    assert_eq!(arcade.score, 12345);
+    assert_eq!(arcade.on_input(), Some(1));
 }
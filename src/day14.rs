@@ -1,9 +0,0 @@
-use advent_of_code_2019::*;
-use std::fs::read_to_string;
-
-pub fn main(input: Option<&str>) -> Result<()> {
-    let _input = read_to_string(input.unwrap_or("input/day14.txt"))?;
-
-    // answer!(14, 1, 42);
-    Ok(())
-}
@@ -1,9 +1,242 @@
-use advent_of_code_2019::*;
-use std::fs::read_to_string;
+use crate::*;
 
-pub fn main(input: Option<&str>) -> Result<()> {
-    let _input = read_to_string(input.unwrap_or("input/day17.txt"))?;
+/// Most characters in the main routine or a movement function
+const MAX_ROUTINE: usize = 20;
 
-    // answer!(17, 1, 42);
-    Ok(())
+pub struct Day17;
+
+impl Solver for Day17 {
+    type Input = Program;
+
+    fn parse(&self, input: &str) -> Result<Self::Input> {
+        Ok(input.parse()?)
+    }
+
+    fn part1(&self, program: &Program) -> Result<Answer> {
+        Ok(view(program)?.alignment().into())
+    }
+
+    fn part2(&self, program: &Program) -> Result<Answer> {
+        let view = view(program)?;
+        let moves = view.path().ok_or_else(|| InputError::new("No vacuum robot in view"))?;
+        let (main, functions) = compress(&moves).ok_or_else(|| InputError::new(moves.join(",")))?;
+
+        // Wake the robot up
+        let mut program = program.clone();
+        program.data[0] = 2;
+
+        let mut robot = AsciiIo::new(program);
+        robot.send_line(&main);
+        for function in functions.iter() {
+            robot.send_line(function);
+        }
+        robot.send_line("n");
+        robot.run()?;
+
+        Ok(robot.answer().ok_or_else(|| InputError::new(robot.lines().join("\n")))?.into())
+    }
+}
+
+/// What the cameras see
+fn view(program: &Program) -> Result<View> {
+    let mut camera = AsciiIo::new(program.fork());
+    camera.run()?;
+    Ok(View::from(camera.lines()))
+}
+
+#[derive(Debug, Clone)]
+struct View {
+    rows: Vec<Vec<char>>,
+}
+
+impl From<&[String]> for View {
+    fn from(lines: &[String]) -> Self {
+        View {
+            rows: lines.iter()
+                .filter(|line| !line.is_empty())
+                .map(|line| line.chars().collect())
+                .collect(),
+        }
+    }
+}
+
+impl View {
+    fn get(&self, x: i32, y: i32) -> char {
+        if x < 0 || y < 0 {
+            return '.';
+        }
+
+        self.rows.get(y as usize)
+            .and_then(|row| row.get(x as usize))
+            .copied()
+            .unwrap_or('.')
+    }
+
+    fn is_scaffold(&self, x: i32, y: i32) -> bool {
+        self.get(x, y) != '.'
+    }
+
+    /// Sum of the products of the coordinates of every scaffold intersection
+    fn alignment(&self) -> i32 {
+        let mut sum = 0;
+        for (y, row) in self.rows.iter().enumerate() {
+            for x in 0..row.len() {
+                let (x, y) = (x as i32, y as i32);
+                if [(0, 0), (1, 0), (-1, 0), (0, 1), (0, -1)].iter()
+                    .all(|(dx, dy)| self.is_scaffold(x + dx, y + dy))
+                {
+                    sum += x * y;
+                }
+            }
+        }
+
+        sum
+    }
+
+    /// The moves that take the robot along the scaffold from where it stands to the end, as
+    /// turns followed by distances, like `["R,8", "L,10"]`
+    fn path(&self) -> Option<Vec<String>> {
+        let (mut x, mut y, mut dir) = self.rows.iter()
+            .enumerate()
+            .find_map(|(y, row)| {
+                row.iter()
+                    .position(|c| "^>v<".contains(*c))
+                    .map(|x| (x as i32, y as i32, row[x]))
+            })
+            .map(|(x, y, c)| (x, y, match c {
+                '^' => (0, -1),
+                '>' => (1, 0),
+                'v' => (0, 1),
+                _ => (-1, 0),
+            }))?;
+
+        let mut moves = Vec::new();
+        loop {
+            let (dx, dy): (i32, i32) = dir;
+            let turn = if self.is_scaffold(x + dy, y - dx) {
+                dir = (dy, -dx);
+                "L"
+            } else if self.is_scaffold(x - dy, y + dx) {
+                dir = (-dy, dx);
+                "R"
+            } else {
+                return Some(moves);
+            };
+
+            let mut distance = 0;
+            while self.is_scaffold(x + dir.0, y + dir.1) {
+                x += dir.0;
+                y += dir.1;
+                distance += 1;
+            }
+
+            moves.push(format!("{},{}", turn, distance));
+        }
+    }
+}
+
+/// Split moves into a main routine calling up to three movement functions, A, B and C
+fn compress(moves: &[String]) -> Option<(String, Vec<String>)> {
+    let mut functions = Vec::new();
+    let mut calls = Vec::new();
+
+    if compress_from(moves, &mut functions, &mut calls) {
+        let main = calls.iter()
+            .map(|call| ["A", "B", "C"][*call])
+            .collect::<Vec<&str>>()
+            .join(",");
+        let functions = functions.iter()
+            .map(|function: &&[String]| function.join(","))
+            .collect();
+        Some((main, functions))
+    } else {
+        None
+    }
+}
+
+fn compress_from<'a>(moves: &'a [String], functions: &mut Vec<&'a [String]>, calls: &mut Vec<usize>) -> bool {
+    // Calls are a letter each, separated by commas
+    if !calls.is_empty() && calls.len() * 2 - 1 > MAX_ROUTINE {
+        return false;
+    }
+
+    if moves.is_empty() {
+        return true;
+    }
+
+    for i in 0..functions.len() {
+        let function = functions[i];
+        if moves.starts_with(function) {
+            calls.push(i);
+            if compress_from(&moves[function.len()..], functions, calls) {
+                return true;
+            }
+            calls.pop();
+        }
+    }
+
+    if functions.len() < 3 {
+        for len in 1..=moves.len() {
+            let function = &moves[..len];
+            if function.join(",").len() > MAX_ROUTINE {
+                break;
+            }
+
+            functions.push(function);
+            calls.push(functions.len() - 1);
+            if compress_from(&moves[len..], functions, calls) {
+                return true;
+            }
+            calls.pop();
+            functions.pop();
+        }
+    }
+
+    false
+}
+
+#[test]
+fn alignment() {
+    let view = View::from(&[
+        "..#..........",
+        "..#..........",
+        "#######...###",
+        "#.#...#...#.#",
+        "#############",
+        "..#...#...#..",
+        "..#####...^..",
+    ].iter().map(|s| s.to_string()).collect::<Vec<String>>()[..]);
+
+    assert_eq!(view.alignment(), 76);
+}
+
+#[test]
+fn path_compression() {
+    let view = View::from(&[
+        "#######...#####",
+        "#.....#...#...#",
+        "#.....#...#...#",
+        "......#...#...#",
+        "......#...###.#",
+        "......#.....#.#",
+        "^########...#.#",
+        "......#.#...#.#",
+        "......#########",
+        "........#...#..",
+        "....#########..",
+        "....#...#......",
+        "....#...#......",
+        "....#...#......",
+        "....#####......",
+    ].iter().map(|s| s.to_string()).collect::<Vec<String>>()[..]);
+
+    let moves = view.path().unwrap();
+    assert_eq!(moves.join(","), "R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2");
+
+    let (main, functions) = compress(&moves).unwrap();
+    let expanded = main.split(',')
+        .map(|call| functions[(call.as_bytes()[0] - b'A') as usize].clone())
+        .collect::<Vec<String>>()
+        .join(",");
+    assert_eq!(expanded, moves.join(","));
 }
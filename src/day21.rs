@@ -1,9 +1,58 @@
-use advent_of_code_2019::*;
-use std::fs::read_to_string;
+use crate::*;
 
-pub fn main(input: Option<&str>) -> Result<()> {
-    let _input = read_to_string(input.unwrap_or("input/day21.txt"))?;
+/// Jump if there's a hole in the next three tiles and ground to land on four tiles ahead
+const WALK: &str = "\
+NOT A J
+NOT B T
+OR T J
+NOT C T
+OR T J
+AND D J
+WALK";
 
-    // answer!(21, 1, 42);
-    Ok(())
+/// As for walking, but only if the droid can either step or jump again after landing
+const RUN: &str = "\
+NOT A J
+NOT B T
+OR T J
+NOT C T
+OR T J
+AND D J
+NOT E T
+NOT T T
+OR H T
+AND T J
+RUN";
+
+pub struct Day21;
+
+impl Solver for Day21 {
+    type Input = Program;
+
+    fn parse(&self, input: &str) -> Result<Self::Input> {
+        Ok(input.parse()?)
+    }
+
+    fn part1(&self, program: &Program) -> Result<Answer> {
+        Ok(hull_damage(program, WALK)?.into())
+    }
+
+    fn part2(&self, program: &Program) -> Result<Answer> {
+        Ok(hull_damage(program, RUN)?.into())
+    }
+}
+
+/// Run a springscript program, returning the hull damage the droid reports. If the droid falls
+/// into space instead, the error holds its last moments as drawn by the droid.
+fn hull_damage(program: &Program, springscript: &str) -> Result<i64> {
+    let mut ascii = AsciiIo::new(program.fork());
+    for line in springscript.lines() {
+        ascii.send_line(line);
+    }
+    ascii.run()?;
+
+    match ascii.answer() {
+        Some(damage) => Ok(damage),
+        None => ioerr!(ascii.lines().join("\n")),
+    }
 }
@@ -1,9 +1,50 @@
-use advent_of_code_2019::*;
-use std::fs::read_to_string;
+use crate::*;
+use crate::network::{Event, Network, NAT};
 
-pub fn main(input: Option<&str>) -> Result<()> {
-    let _input = read_to_string(input.unwrap_or("input/day23.txt"))?;
+pub struct Day23;
 
-    // answer!(23, 1, 42);
-    Ok(())
+impl Solver for Day23 {
+    type Input = Program;
+
+    fn parse(&self, input: &str) -> Result<Self::Input> {
+        Ok(input.parse()?)
+    }
+
+    /// The Y value of the first packet sent to the NAT
+    fn part1(&self, program: &Program) -> Result<Answer> {
+        let mut first_to_nat = None;
+
+        Network::new(program, 50).run(|event| {
+            if let Event::Sent(packet) = event {
+                if packet.to == NAT as i64 {
+                    first_to_nat = Some(packet.y);
+                    return false;
+                }
+            }
+            true
+        })?;
+
+        Ok(first_to_nat.ok_or_else(|| InputError::new("Nothing sent to the NAT"))?.into())
+    }
+
+    /// The first Y value the NAT sends twice in a row
+    fn part2(&self, program: &Program) -> Result<Answer> {
+        let mut last_from_nat = None;
+        let mut repeated_from_nat = None;
+
+        Network::new(program, 50).run(|event| {
+            if let Event::Sent(packet) = event {
+                if packet.from == NAT {
+                    if last_from_nat == Some(packet.y) {
+                        repeated_from_nat = Some(packet.y);
+                        return false;
+                    }
+                    last_from_nat = Some(packet.y);
+                }
+            }
+            true
+        })?;
+
+        Ok(repeated_from_nat.ok_or_else(|| InputError::new("The NAT never repeated itself"))?.into())
+    }
 }
@@ -0,0 +1,152 @@
+use crate::*;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs::read_to_string;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Where puzzle inputs are kept, unless `DIR_VAR` says otherwise
+pub const DEFAULT_DIR: &str = "input";
+/// The environment variable naming the directory puzzle inputs are kept in
+pub const DIR_VAR: &str = "AOC_INPUT_DIR";
+
+/// Where a puzzle input is read from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Source {
+    /// Standard input, written `-`
+    Stdin,
+    File(PathBuf),
+}
+
+impl From<&str> for Source {
+    fn from(s: &str) -> Self {
+        match s {
+            "-" => Source::Stdin,
+            path => Source::File(PathBuf::from(path)),
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Stdin => write!(f, "standard input"),
+            Source::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// A day's puzzle input isn't where it was expected
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MissingInput {
+    pub day: u8,
+    pub path: PathBuf,
+}
+
+impl fmt::Display for MissingInput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "No puzzle input for day {}: expected it at {}", self.day, self.path.display())
+    }
+}
+
+impl Error for MissingInput {}
+
+/// Where each day's puzzle input comes from: a file named for the day in the input directory,
+/// unless the day, or one part of it, is given another
+#[derive(Debug, Default)]
+pub struct Inputs {
+    dir: Option<PathBuf>,
+    /// By day, and part if only for one part
+    overrides: BTreeMap<(u8, Option<u8>), Source>,
+    /// Standard input can only be read once, so it's kept for every day that reads it
+    stdin: RefCell<Option<String>>,
+}
+
+impl Inputs {
+    /// Inputs from the files in `dir`
+    pub fn in_dir<P: Into<PathBuf>>(dir: P) -> Self {
+        Inputs { dir: Some(dir.into()), ..Inputs::default() }
+    }
+
+    /// The directory inputs are read from: the one given, or else `DIR_VAR`, or else `DEFAULT_DIR`
+    pub fn dir(&self) -> PathBuf {
+        self.dir.clone()
+            .or_else(|| env::var_os(DIR_VAR).map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_DIR))
+    }
+
+    /// Read a day's input, or one part's, from `source` instead
+    pub fn set(&mut self, day: u8, part: Option<u8>, source: Source) {
+        self.overrides.insert((day, part), source);
+    }
+
+    /// Where a part of a day reads its input from
+    pub fn source(&self, day: u8, part: u8) -> Source {
+        self.overrides.get(&(day, Some(part)))
+            .or_else(|| self.overrides.get(&(day, None)))
+            .cloned()
+            .unwrap_or_else(|| Source::File(self.path(day)))
+    }
+
+    /// The file a day's input is in, unless it's given another
+    pub fn path(&self, day: u8) -> PathBuf {
+        self.dir().join(format!("day{:02}.txt", day))
+    }
+
+    /// Read a day's input from `source`
+    pub fn read(&self, day: u8, source: &Source) -> Result<String> {
+        self.read_source(source).map_err(|e| match (e.kind(), source) {
+            (io::ErrorKind::NotFound, Source::File(path)) => MissingInput { day, path: path.clone() }.into(),
+            _ => e.into(),
+        })
+    }
+
+    /// Read an input that isn't any day's, from `source`
+    pub fn read_source(&self, source: &Source) -> io::Result<String> {
+        match source {
+            Source::File(path) => read_file(path),
+            Source::Stdin => {
+                let mut stdin = self.stdin.borrow_mut();
+                if stdin.is_none() {
+                    let mut input = String::new();
+                    io::stdin().read_to_string(&mut input)?;
+                    *stdin = Some(input);
+                }
+                Ok(stdin.clone().unwrap_or_default())
+            },
+        }
+    }
+}
+
+/// Read a file, saying which one in any error
+fn read_file(path: &Path) -> io::Result<String> {
+    read_to_string(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
+
+#[test]
+fn sources() -> Result<()> {
+    let mut inputs = Inputs::in_dir("input");
+    inputs.set(2, None, Source::from("-"));
+    inputs.set(3, Some(2), Source::from("other/day03.txt"));
+
+    assert_eq!(inputs.source(1, 1), Source::File(PathBuf::from("input/day01.txt")));
+    assert_eq!(inputs.source(2, 2), Source::Stdin);
+    assert_eq!(inputs.source(3, 1), Source::File(PathBuf::from("input/day03.txt")));
+    assert_eq!(inputs.source(3, 2), Source::File(PathBuf::from("other/day03.txt")));
+    assert!(inputs.read(1, &inputs.source(1, 1))?.starts_with("125050"));
+
+    let missing = Inputs::in_dir("nowhere");
+    let error = missing.read(6, &missing.source(6, 1)).unwrap_err();
+    assert_eq!(error.downcast_ref(), Some(&MissingInput { day: 6, path: PathBuf::from("nowhere/day06.txt") }));
+    assert_eq!(error.to_string(), "No puzzle input for day 6: expected it at nowhere/day06.txt");
+
+    // Anything else that goes wrong reading a file is an I/O error, naming the file
+    let error = inputs.read(1, &Source::from("input")).unwrap_err();
+    assert_eq!(error.downcast_ref::<io::Error>().map(io::Error::kind), Some(io::ErrorKind::IsADirectory));
+    assert!(error.to_string().starts_with("input: "), "{}", error);
+    assert_eq!(inputs.read_source(&Source::from("nowhere.txt")).unwrap_err().kind(), io::ErrorKind::NotFound);
+
+    Ok(())
+}
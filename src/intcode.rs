@@ -1,38 +1,288 @@
-use crate::*;
+use crate::InputError;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+pub mod analysis;
+pub mod ascii;
+pub mod asm;
+pub mod budget;
+pub mod compile;
+pub mod debug;
+pub mod device;
+pub mod disasm;
+pub mod extension;
+#[cfg(test)]
+mod fuzz;
+pub mod memory;
+pub mod network;
+pub mod pipeline;
+pub mod replay;
+pub mod snapshot;
+pub mod solve;
+pub mod trace;
+
+pub use ascii::AsciiIo;
+pub use budget::{Budget, CancelToken, Exhausted, Outcome};
+pub use compile::Compiled;
+pub use device::Device;
+pub use extension::{CustomOp, Effect, InstructionSet};
+pub use memory::Memory;
+pub use pipeline::Pipeline;
+pub use snapshot::Snapshot;
+pub use trace::{Executed, Observer};
+
+/// The highest address a program may use. No puzzle program comes close, and a limit keeps a
+/// wild write from trying to allocate more memory than there is.
+pub const MAX_ADDRESS: usize = (1 << 24) - 1;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Program{
-    pub data: Vec<usize>,
+    pub data: Memory,
     pub head: usize,
+    pub relative_base: i64,
+    pub input: VecDeque<i64>,
+    /// Number of instructions executed so far
+    pub steps: usize,
+    /// Fail with `IntcodeError::StepLimitExceeded` rather than execute more instructions than this.
+    /// Runs within a budget stop with `Exhausted::Steps` instead.
+    pub step_limit: Option<usize>,
+    /// Custom instructions the program may use as well as the built-in ones
+    pub instruction_set: InstructionSet,
+}
+
+/// Why the machine stopped running
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum State {
+    /// Suspended on an input instruction with an empty input queue. Push some input and run again
+    /// to resume.
+    NeedsInput,
+    Output(i64),
+    Halted,
 }
 
 impl Program {
-    pub fn step(&mut self) -> Option<()> {
-        let output = Command {
-            opcode: OpCode::from(*self.data.get(self.head)?),
-            input_a: *self.data.get(*self.data.get(self.head + 1)?)?,
-            input_b: *self.data.get(*self.data.get(self.head + 2)?)?,
-        }.output()?;
+    pub fn new(data: Vec<i64>) -> Self {
+        Program {
+            data: Memory::from(data),
+            head: 0,
+            relative_base: 0,
+            input: VecDeque::new(),
+            steps: 0,
+            step_limit: None,
+            instruction_set: InstructionSet::default(),
+        }
+    }
+
+    /// Execute one instruction. Gives the state the machine stopped in, or None if it carries on.
+    pub fn step(&mut self) -> Result<Option<State>, IntcodeError> {
+        self.step_with(&mut ())
+    }
+
+    /// Step, reporting the executed instruction to an observer
+    pub fn step_with<O: Observer>(&mut self, observer: &mut O) -> Result<Option<State>, IntcodeError> {
+        let ip = self.head;
+        let word = self.data[ip];
+
+        if let Some(limit) = self.step_limit {
+            if self.steps >= limit {
+                return Err(IntcodeError::StepLimitExceeded { ip, instruction: word, limit });
+            }
+        }
+
+        let instruction = self.instruction_set.decode(ip, word)?;
+        let mut operands = [0; 3];
+        let mut written = None;
+        let mut next = ip + instruction.opcode.size();
+
+        let state = match instruction.opcode {
+            OpCode::Add | OpCode::Mul | OpCode::LessThan | OpCode::Equals => {
+                let command = Command {
+                    opcode: instruction.opcode,
+                    input_a: self.param(&instruction, 0)?,
+                    input_b: self.param(&instruction, 1)?,
+                };
+                let output = command.output()
+                    .ok_or(IntcodeError::Overflow { ip, instruction: word })?;
+
+                let address = self.write(&instruction, 2, output)?;
+                operands = [command.input_a, command.input_b, address as i64];
+                written = Some((address, output));
+                None
+            },
+            OpCode::Input => {
+                let value = match self.input.front() {
+                    Some(value) => *value,
+                    None => return Ok(Some(State::NeedsInput)),
+                };
+                let address = self.write(&instruction, 0, value)?;
+                self.input.pop_front();
+
+                operands[0] = address as i64;
+                written = Some((address, value));
+                None
+            },
+            OpCode::Output => {
+                let value = self.param(&instruction, 0)?;
+                operands[0] = value;
+                Some(State::Output(value))
+            },
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+                // The target is only resolved if the jump is taken, so a jump that isn't taken can't
+                // fault on it
+                let condition = self.param(&instruction, 0)?;
+                let mut target = self.data[ip + 2];
+                if (condition != 0) == (instruction.opcode == OpCode::JumpIfTrue) {
+                    target = self.param(&instruction, 1)?;
+                    next = self.to_address(&instruction, target)?;
+                }
+
+                operands = [condition, target, 0];
+                None
+            },
+            OpCode::AdjustBase => {
+                let offset = self.param(&instruction, 0)?;
+                self.relative_base = self.relative_base.checked_add(offset)
+                    .ok_or(IntcodeError::Overflow { ip, instruction: word })?;
+
+                operands[0] = offset;
+                None
+            },
+            OpCode::Halt => {
+                observer.on_step(&Executed { ip, instruction, operands, written });
+                return Ok(Some(State::Halted));
+            },
+            OpCode::Custom(op) => {
+                for (n, operand) in operands.iter_mut().enumerate().take(op.arity) {
+                    *operand = match op.writes(n) {
+                        true => self.write_address(&instruction, n)? as i64,
+                        false => self.param(&instruction, n)?,
+                    };
+                }
+
+                let handler = self.instruction_set.handler(op.code)
+                    .ok_or(IntcodeError::UnknownOpCode { ip, instruction: word })?;
+                let Effect { state, jump } = handler(self, &operands[..op.arity])?;
+                written = instruction.opcode.write_param().map(|n| (operands[n] as usize, self.data[operands[n] as usize]));
 
-        let output_index = *self.data.get(self.head + 3)?;
-        *self.data.get_mut(output_index)? = output;
+                match state {
+                    Some(State::NeedsInput) => return Ok(state),
+                    Some(State::Halted) => {
+                        observer.on_step(&Executed { ip, instruction, operands, written });
+                        return Ok(state);
+                    },
+                    _ => (),
+                }
+                if let Some(address) = jump {
+                    next = checked_address(ip, word, i64::try_from(address).unwrap_or(i64::MAX))?;
+                }
+                state
+            },
+        };
 
-        self.head += 4;
+        self.head = next;
+        self.steps += 1;
+        observer.on_step(&Executed { ip, instruction, operands, written });
 
-        Some(())
+        Ok(state)
     }
 
-    pub fn run(&mut self) {
-        while let Some(()) = self.step() {
+    /// Step until the program outputs a value, needs input, or halts
+    pub fn run(&mut self) -> Result<State, IntcodeError> {
+        self.run_with(&mut ())
+    }
+
+    /// Run, reporting each executed instruction to an observer
+    pub fn run_with<O: Observer>(&mut self, observer: &mut O) -> Result<State, IntcodeError> {
+        loop {
+            if let Some(state) = self.step_with(observer)? {
+                return Ok(state);
+            }
+        }
+    }
+
+    /// Run until the program halts, collecting its output. Fails with
+    /// `IntcodeError::MissingInput` if it runs out of input on the way.
+    pub fn run_to_halt(&mut self) -> Result<Vec<i64>, IntcodeError> {
+        self.run_to_halt_with(&mut ())
+    }
+
+    /// Run to halt, reporting each executed instruction to an observer
+    pub fn run_to_halt_with<O: Observer>(&mut self, observer: &mut O) -> Result<Vec<i64>, IntcodeError> {
+        let mut output = Vec::new();
+
+        loop {
+            match self.run_with(observer)? {
+                State::Output(value) => output.push(value),
+                State::Halted => return Ok(output),
+                State::NeedsInput => return Err(self.missing_input()),
+            }
+        }
+    }
 
+    /// The error for a program that needs input when there is none to give it
+    pub(crate) fn missing_input(&self) -> IntcodeError {
+        IntcodeError::MissingInput { ip: self.head, instruction: self.data[self.head] }
+    }
+
+    /// Queue a value for the program's next input instruction
+    pub fn push_input(&mut self, value: i64) {
+        self.input.push_back(value);
+    }
+
+    /// Queue values for the program's input instructions, in order
+    pub fn extend_input<I: IntoIterator<Item = i64>>(&mut self, values: I) {
+        self.input.extend(values);
+    }
+
+    /// The address of the nth parameter of the instruction at `head`, resolved through its mode.
+    /// An immediate parameter resolves to the address of the parameter itself.
+    fn address(&self, instruction: &Instruction, n: usize) -> Result<usize, IntcodeError> {
+        let index = self.head + 1 + n;
+        match instruction.modes[n] {
+            Mode::Position => self.to_address(instruction, self.data[index]),
+            Mode::Immediate => Ok(index),
+            Mode::Relative => match self.relative_base.checked_add(self.data[index]) {
+                Some(address) => self.to_address(instruction, address),
+                None => Err(IntcodeError::Overflow { ip: self.head, instruction: instruction.word }),
+            },
         }
     }
 
-    pub fn first(&self) -> usize {
-        *self.data.get(0).unwrap()
+    /// The address the nth parameter of the instruction at `head` writes to
+    fn write_address(&self, instruction: &Instruction, n: usize) -> Result<usize, IntcodeError> {
+        if instruction.modes[n] == Mode::Immediate {
+            return Err(IntcodeError::ImmediateWrite { ip: self.head, instruction: instruction.word });
+        }
+
+        self.address(instruction, n)
+    }
+
+    /// The value of the nth parameter of the instruction at `head`
+    fn param(&self, instruction: &Instruction, n: usize) -> Result<i64, IntcodeError> {
+        Ok(self.data[self.address(instruction, n)?])
     }
 
-    pub fn change_values(mut self, x: usize, y: usize) -> Self {
+    /// Write to the address of the nth parameter of the instruction at `head`, returning the address
+    fn write(&mut self, instruction: &Instruction, n: usize, value: i64) -> Result<usize, IntcodeError> {
+        let address = self.write_address(instruction, n)?;
+        self.data[address] = value;
+
+        Ok(address)
+    }
+
+    /// Negative addresses, and those past `MAX_ADDRESS`, are out of bounds
+    fn to_address(&self, instruction: &Instruction, address: i64) -> Result<usize, IntcodeError> {
+        checked_address(self.head, instruction.word, address)
+    }
+
+    pub fn first(&self) -> i64 {
+        self.data[0]
+    }
+
+    pub fn change_values(mut self, x: i64, y: i64) -> Self {
         self.data[1] = x;
         self.data[2] = y;
 
@@ -40,73 +290,454 @@ impl Program {
     }
 }
 
-impl From<String> for Program {
-    fn from(s: String) -> Self {
-        let data = s.split(',')
-            .filter_map(|n| n.parse::<usize>().ok())
-            .collect();
+/// Check that an address an instruction uses is in bounds
+pub(crate) fn checked_address(ip: usize, instruction: i64, address: i64) -> Result<usize, IntcodeError> {
+    if address < 0 {
+        Err(IntcodeError::NegativeAddress { ip, instruction, address })
+    } else if address as u64 > MAX_ADDRESS as u64 {
+        Err(IntcodeError::AddressTooLarge { ip, instruction, address })
+    } else {
+        Ok(address as usize)
+    }
+}
 
-        Program {
-            data,
-            head: 0,
+/// A fault while executing an Intcode program, located by the instruction pointer `ip` and the
+/// raw `instruction` word found there
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum IntcodeError {
+    UnknownOpCode { ip: usize, instruction: i64 },
+    InvalidMode { ip: usize, instruction: i64 },
+    ImmediateWrite { ip: usize, instruction: i64 },
+    NegativeAddress { ip: usize, instruction: i64, address: i64 },
+    AddressTooLarge { ip: usize, instruction: i64, address: i64 },
+    Overflow { ip: usize, instruction: i64 },
+    MissingInput { ip: usize, instruction: i64 },
+    StepLimitExceeded { ip: usize, instruction: i64, limit: usize },
+}
+
+impl IntcodeError {
+    pub fn ip(&self) -> usize {
+        match *self {
+            IntcodeError::UnknownOpCode { ip, .. }
+            | IntcodeError::InvalidMode { ip, .. }
+            | IntcodeError::ImmediateWrite { ip, .. }
+            | IntcodeError::NegativeAddress { ip, .. }
+            | IntcodeError::AddressTooLarge { ip, .. }
+            | IntcodeError::Overflow { ip, .. }
+            | IntcodeError::MissingInput { ip, .. }
+            | IntcodeError::StepLimitExceeded { ip, .. } => ip,
+        }
+    }
+
+    pub fn instruction(&self) -> i64 {
+        match *self {
+            IntcodeError::UnknownOpCode { instruction, .. }
+            | IntcodeError::InvalidMode { instruction, .. }
+            | IntcodeError::ImmediateWrite { instruction, .. }
+            | IntcodeError::NegativeAddress { instruction, .. }
+            | IntcodeError::AddressTooLarge { instruction, .. }
+            | IntcodeError::Overflow { instruction, .. }
+            | IntcodeError::MissingInput { instruction, .. }
+            | IntcodeError::StepLimitExceeded { instruction, .. } => instruction,
+        }
+    }
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntcodeError::UnknownOpCode { .. } => write!(f, "Unknown opcode")?,
+            IntcodeError::InvalidMode { .. } => write!(f, "Invalid parameter mode")?,
+            IntcodeError::ImmediateWrite { .. } => write!(f, "Write in immediate mode")?,
+            IntcodeError::NegativeAddress { address, .. } => write!(f, "Negative address {}", address)?,
+            IntcodeError::AddressTooLarge { address, .. } => write!(f, "Address {} too large", address)?,
+            IntcodeError::Overflow { .. } => write!(f, "Arithmetic overflow")?,
+            IntcodeError::MissingInput { .. } => write!(f, "No input available")?,
+            IntcodeError::StepLimitExceeded { limit, .. } => write!(f, "Step limit of {} exceeded", limit)?,
         }
+
+        write!(f, " at {}: instruction {}", self.ip(), self.instruction())
+    }
+}
+
+impl Error for IntcodeError {}
+
+impl FromStr for Program {
+    type Err = InputError<String>;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let data = s.trim()
+            .split(',')
+            .map(|n| n.trim().parse::<i64>().map_err(|_| InputError::new(n.to_string())))
+            .collect::<std::result::Result<Vec<i64>, _>>()?;
+
+        Ok(Program::new(data))
     }
 }
 
 pub struct Command {
     pub opcode: OpCode,
-    pub input_a: usize,
-    pub input_b: usize,
+    pub input_a: i64,
+    pub input_b: i64,
 }
 
 impl Command {
-    pub fn output(&self) -> Option<usize> {
+    /// The result of an arithmetic or comparison opcode, or None for any other opcode, or if the
+    /// result overflows
+    pub fn output(&self) -> Option<i64> {
         match self.opcode {
-            OpCode::Add => Some(self.input_a + self.input_b),
-            OpCode::Mul => Some(self.input_a * self.input_b),
-            OpCode::Halt => None,
+            OpCode::Add => self.input_a.checked_add(self.input_b),
+            OpCode::Mul => self.input_a.checked_mul(self.input_b),
+            OpCode::LessThan => Some((self.input_a < self.input_b) as i64),
+            OpCode::Equals => Some((self.input_a == self.input_b) as i64),
+            _ => None,
         }
     }
 }
 
-impl From<[usize; 3]> for Command {
-    fn from(arr: [usize; 3]) -> Self {
-        Command {
-            opcode: OpCode::from(arr[0]),
+impl TryFrom<[i64; 3]> for Command {
+    type Error = InputError<i64>;
+    fn try_from(arr: [i64; 3]) -> std::result::Result<Self, Self::Error> {
+        Ok(Command {
+            opcode: OpCode::try_from(arr[0])?,
             input_a: arr[1],
             input_b: arr[2],
+        })
+    }
+}
+
+/// A decoded instruction word: the opcode in the last two digits, and one parameter mode per
+/// digit above those, read right to left.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Instruction {
+    pub word: i64,
+    pub opcode: OpCode,
+    pub modes: [Mode; 3],
+}
+
+impl Instruction {
+    /// Encode an opcode and the modes of its parameters into an instruction word
+    pub fn new(opcode: OpCode, modes: [Mode; 3]) -> Self {
+        let word = modes.iter()
+            .rev()
+            .fold(0, |word, mode| word * 10 + mode.digit()) * 100 + opcode.code();
+
+        Instruction { word, opcode, modes }
+    }
+
+    /// Decode the instruction word found at address `ip`, knowing only the built-in opcodes
+    pub fn decode(ip: usize, word: i64) -> Result<Self, IntcodeError> {
+        Instruction::decode_with(ip, word, |code| OpCode::try_from(code).ok())
+    }
+
+    /// Decode an instruction word, looking its opcode up with `opcode`
+    pub(crate) fn decode_with<F: Fn(i64) -> Option<OpCode>>(ip: usize, word: i64, opcode: F) -> Result<Self, IntcodeError> {
+        let opcode = opcode(word % 100)
+            .ok_or(IntcodeError::UnknownOpCode { ip, instruction: word })?;
+
+        let mut modes = [Mode::Position; 3];
+        let mut digits = word / 100;
+        for mode in modes.iter_mut() {
+            *mode = Mode::try_from(digits % 10)
+                .map_err(|_| IntcodeError::InvalidMode { ip, instruction: word })?;
+            digits /= 10;
+        }
+
+        // Any digits left over would be modes for parameters that don't exist
+        if digits != 0 {
+            return Err(IntcodeError::InvalidMode { ip, instruction: word });
         }
+
+        Ok(Instruction { word, opcode, modes })
     }
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum OpCode {
     Add,
     Mul,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustBase,
     Halt,
+    /// An instruction from a program's `InstructionSet`
+    Custom(CustomOp),
 }
 
-impl From<usize> for OpCode {
-    fn from(u: usize) -> Self {
-        match u {
-            1 => OpCode::Add,
-            2 => OpCode::Mul,
-            99 => OpCode::Halt,
-            x => unreachable!("Something went wrong!: {}", x),
+impl OpCode {
+    /// The built-in opcodes
+    pub const ALL: [OpCode; 10] = [
+        OpCode::Add,
+        OpCode::Mul,
+        OpCode::Input,
+        OpCode::Output,
+        OpCode::JumpIfTrue,
+        OpCode::JumpIfFalse,
+        OpCode::LessThan,
+        OpCode::Equals,
+        OpCode::AdjustBase,
+        OpCode::Halt,
+    ];
+
+    /// The number the opcode is encoded as in the last two digits of an instruction word
+    pub fn code(self) -> i64 {
+        match self {
+            OpCode::Add => 1,
+            OpCode::Mul => 2,
+            OpCode::Input => 3,
+            OpCode::Output => 4,
+            OpCode::JumpIfTrue => 5,
+            OpCode::JumpIfFalse => 6,
+            OpCode::LessThan => 7,
+            OpCode::Equals => 8,
+            OpCode::AdjustBase => 9,
+            OpCode::Halt => 99,
+            OpCode::Custom(op) => op.code,
         }
     }
+
+    /// The opcode with the given short assembly name
+    pub fn from_mnemonic(s: &str) -> Option<Self> {
+        OpCode::ALL.iter().copied().find(|opcode| opcode.mnemonic() == s)
+    }
+
+    /// The number of parameters the instruction takes
+    pub fn arity(self) -> usize {
+        match self {
+            OpCode::Add | OpCode::Mul | OpCode::LessThan | OpCode::Equals => 3,
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse => 2,
+            OpCode::Input | OpCode::Output | OpCode::AdjustBase => 1,
+            OpCode::Halt => 0,
+            OpCode::Custom(op) => op.arity,
+        }
+    }
+
+    /// The number of cells the instruction occupies, including the instruction word itself
+    pub fn size(self) -> usize {
+        self.arity() + 1
+    }
+
+    /// The index of the parameter the instruction writes to, if any. For a custom instruction that
+    /// writes to more than one, the first of them.
+    pub fn write_param(self) -> Option<usize> {
+        match self {
+            OpCode::Add | OpCode::Mul | OpCode::LessThan | OpCode::Equals => Some(2),
+            OpCode::Input => Some(0),
+            OpCode::Custom(op) => (0..op.arity).find(|n| op.writes(*n)),
+            _ => None,
+        }
+    }
+
+    /// Whether the instruction writes to parameter `n`
+    pub fn writes(self, n: usize) -> bool {
+        match self {
+            OpCode::Custom(op) => n < op.arity && op.writes(n),
+            _ => self.write_param() == Some(n),
+        }
+    }
+
+    /// Short assembly name of the instruction
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            OpCode::Add => "add",
+            OpCode::Mul => "mul",
+            OpCode::Input => "in",
+            OpCode::Output => "out",
+            OpCode::JumpIfTrue => "jt",
+            OpCode::JumpIfFalse => "jf",
+            OpCode::LessThan => "lt",
+            OpCode::Equals => "eq",
+            OpCode::AdjustBase => "arb",
+            OpCode::Halt => "hlt",
+            OpCode::Custom(op) => op.mnemonic,
+        }
+    }
+}
+
+impl TryFrom<i64> for OpCode {
+    type Error = InputError<i64>;
+    fn try_from(i: i64) -> std::result::Result<Self, Self::Error> {
+        OpCode::ALL.iter()
+            .copied()
+            .find(|opcode| opcode.code() == i)
+            .ok_or_else(|| InputError::new(i))
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Mode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl Mode {
+    /// The digit the mode is encoded as in an instruction word
+    pub fn digit(self) -> i64 {
+        match self {
+            Mode::Position => 0,
+            Mode::Immediate => 1,
+            Mode::Relative => 2,
+        }
+    }
+
+    /// Prefix marking an operand's mode in assembly. Position mode operands are bare.
+    pub fn sigil(self) -> &'static str {
+        match self {
+            Mode::Position => "",
+            Mode::Immediate => "#",
+            Mode::Relative => "@",
+        }
+    }
+}
+
+impl TryFrom<i64> for Mode {
+    type Error = InputError<i64>;
+    fn try_from(i: i64) -> std::result::Result<Self, Self::Error> {
+        Ok(match i {
+            0 => Mode::Position,
+            1 => Mode::Immediate,
+            2 => Mode::Relative,
+            x => return Err(InputError::new(x)),
+        })
+    }
 }
 
 #[test]
-fn first_steps() -> Result<()> {
-    let mut program = Program::from("1,9,10,3,2,3,11,0,99,30,40,50".to_string());
-    let expected = Program::from("1,9,10,70,2,3,11,0,99,30,40,50".to_string());
+fn first_steps() -> crate::Result<()> {
+    let mut program = "1,9,10,3,2,3,11,0,99,30,40,50".parse::<Program>()?;
+    let expected = "1,9,10,70,2,3,11,0,99,30,40,50".parse::<Program>()?;
 
     program.step().unwrap();
     assert_eq!(program.data, expected.data);
 
-    let expected = Program::from("3500,9,10,70,2,3,11,0,99,30,40,50".to_string());
+    let expected = "3500,9,10,70,2,3,11,0,99,30,40,50".parse::<Program>()?;
     program.step().unwrap();
     assert_eq!(program.data, expected.data);
 
     Ok(())
 }
+
+#[test]
+fn parameter_modes() -> crate::Result<()> {
+    let mut program = "1002,4,3,4,33".parse::<Program>()?;
+    program.run_to_halt()?;
+    assert_eq!(program.data[4], 99);
+
+    let mut program = "1101,100,-1,4,0".parse::<Program>()?;
+    program.run_to_halt()?;
+    assert_eq!(program.data[4], 99);
+
+    let mut program = "109,7,21101,2,3,0,99,0".parse::<Program>()?;
+    program.run_to_halt()?;
+    assert_eq!(program.relative_base, 7);
+    assert_eq!(program.data[7], 5);
+
+    Ok(())
+}
+
+#[test]
+fn input_output() -> crate::Result<()> {
+    let mut program = "3,0,4,0,99".parse::<Program>()?;
+    program.push_input(1234);
+    assert_eq!(program.run_to_halt()?, vec![1234]);
+
+    // Outputs 1 if the input is equal to 8, and 0 otherwise
+    for (input, expected) in [(7, 0), (8, 1), (9, 0)].iter() {
+        let mut program = "3,9,8,9,10,9,4,9,99,-1,8".parse::<Program>()?;
+        program.push_input(*input);
+        assert_eq!(program.run_to_halt()?, vec![*expected]);
+    }
+
+    // Outputs 0 if the input is zero, and 1 otherwise
+    for (input, expected) in [(0, 0), (5, 1)].iter() {
+        let mut program = "3,3,1105,-1,9,1101,0,0,12,4,12,99,1".parse::<Program>()?;
+        program.push_input(*input);
+        assert_eq!(program.run_to_halt()?, vec![*expected]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn large_memory() -> crate::Result<()> {
+    let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+    let mut program = quine.parse::<Program>()?;
+    assert_eq!(program.run_to_halt()?, quine.parse::<Program>()?.data.iter().copied().collect::<Vec<_>>());
+
+    let mut program = "104,1125899906842624,99".parse::<Program>()?;
+    assert_eq!(program.run_to_halt()?, vec![1125899906842624]);
+
+    let mut program = "1102,34915192,34915192,7,4,7,99,0".parse::<Program>()?;
+    assert_eq!(program.run_to_halt()?, vec![1219070632396864]);
+
+    Ok(())
+}
+
+#[test]
+fn parse_error() {
+    let err = "1,2,x3,99".parse::<Program>().unwrap_err();
+    assert_eq!(err.to_string(), "Invalid input: '\"x3\"'");
+}
+
+#[test]
+fn errors() -> crate::Result<()> {
+    let mut program = "1,0,0,0,42".parse::<Program>()?;
+    assert_eq!(program.run(), Err(IntcodeError::UnknownOpCode { ip: 4, instruction: 42 }));
+
+    let mut program = "301,0,0,0,99".parse::<Program>()?;
+    assert_eq!(program.run(), Err(IntcodeError::InvalidMode { ip: 0, instruction: 301 }));
+
+    let mut program = "10001,0,0,0,99".parse::<Program>()?;
+    assert_eq!(program.run(), Err(IntcodeError::ImmediateWrite { ip: 0, instruction: 10001 }));
+
+    let mut program = "1,-3,0,0,99".parse::<Program>()?;
+    assert_eq!(program.run(), Err(IntcodeError::NegativeAddress { ip: 0, instruction: 1, address: -3 }));
+
+    let mut program = "1,16777216,0,0,99".parse::<Program>()?;
+    assert_eq!(program.run(), Err(IntcodeError::AddressTooLarge { ip: 0, instruction: 1, address: 16777216 }));
+
+    let mut program = "1102,4611686018427387904,2,0,99".parse::<Program>()?;
+    assert_eq!(program.run(), Err(IntcodeError::Overflow { ip: 0, instruction: 1102 }));
+
+    let mut program = "109,9223372036854775807,109,1,99".parse::<Program>()?;
+    assert_eq!(program.run(), Err(IntcodeError::Overflow { ip: 2, instruction: 109 }));
+
+    // A jump that isn't taken never looks at its target
+    let mut program = "1106,1,-5,104,7,99".parse::<Program>()?;
+    assert_eq!(program.run(), Ok(State::Output(7)));
+
+    let mut program = "1105,1,0".parse::<Program>()?;
+    program.step_limit = Some(10);
+    assert_eq!(program.run(), Err(IntcodeError::StepLimitExceeded { ip: 0, instruction: 1105, limit: 10 }));
+    assert_eq!(program.steps, 10);
+
+    let err = crate::BoxError::from(IntcodeError::UnknownOpCode { ip: 4, instruction: 42 });
+    assert_eq!(err.to_string(), "Unknown opcode at 4: instruction 42");
+
+    Ok(())
+}
+
+#[test]
+fn suspend_and_resume() -> crate::Result<()> {
+    // Adds pairs of inputs together until it reads a zero
+    let mut program = "3,17,1006,17,16,3,18,1,17,18,19,4,19,1105,1,0,99".parse::<Program>()?;
+    assert_eq!(program.run()?, State::NeedsInput);
+
+    program.push_input(2);
+    assert_eq!(program.run()?, State::NeedsInput);
+    program.push_input(3);
+    assert_eq!(program.run()?, State::Output(5));
+
+    program.extend_input(vec![10, 20, 0]);
+    assert_eq!(program.run()?, State::Output(30));
+    assert_eq!(program.run()?, State::Halted);
+
+    let mut program = "3,0,99".parse::<Program>()?;
+    assert_eq!(program.run_to_halt(), Err(IntcodeError::MissingInput { ip: 0, instruction: 3 }));
+
+    Ok(())
+}
@@ -0,0 +1,384 @@
+use crate::intcode::disasm::{decode, Line, Operand};
+use crate::intcode::{InstructionSet, Mode, OpCode, Program};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::{self, Write};
+
+/// A run of instructions that always execute together, entered only at the top
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Block {
+    pub start: usize,
+    /// Addresses of the block's instructions, in order
+    pub instructions: Vec<usize>,
+    /// Starts of the blocks control can pass to next
+    pub successors: Vec<usize>,
+    /// Whether the block ends in a jump whose target is only known at run time
+    pub indirect: bool,
+}
+
+/// A write into an instruction that can execute after the write
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Modification {
+    /// Address of the writing instruction
+    pub site: usize,
+    /// Address written
+    pub address: usize,
+    /// Address of the instruction it writes into
+    pub instruction: usize,
+}
+
+/// What can be learned about a program without running it.
+///
+/// Control flow is followed from address 0. Jumps with immediate targets and conditions are
+/// resolved; any other jump target is only known at run time, so code reached only that way, like
+/// the return side of a call, isn't found. Writes in position mode have known addresses, while
+/// writes in relative mode could land anywhere.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Analysis {
+    /// Every reachable instruction, by address
+    pub instructions: BTreeMap<usize, Line>,
+    /// The control flow graph, by block start
+    pub blocks: BTreeMap<usize, Block>,
+    /// Addresses written in position mode, with the addresses of the instructions writing them
+    pub written: BTreeMap<usize, BTreeSet<usize>>,
+    /// Instructions that write in relative mode
+    pub relative_writes: BTreeSet<usize>,
+    /// Jumps whose target is only known at run time, and custom instructions, which can go anywhere
+    pub indirect_jumps: BTreeSet<usize>,
+    pub inputs: BTreeSet<usize>,
+    pub outputs: BTreeSet<usize>,
+    /// Reachable addresses that don't hold a valid instruction
+    pub invalid: BTreeSet<usize>,
+    /// Known writes to code, or to reachable invalid instructions, that run afterward
+    pub modifications: Vec<Modification>,
+}
+
+impl Analysis {
+    /// Whether the program is known to write to its own code before running it
+    pub fn self_modifying(&self) -> bool {
+        !self.modifications.is_empty()
+    }
+
+    /// Whether the program might write to its own code, counting writes that could land anywhere
+    pub fn may_self_modify(&self) -> bool {
+        self.self_modifying() || !self.relative_writes.is_empty()
+    }
+
+    /// The control flow graph in Graphviz DOT
+    pub fn dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph intcode {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=monospace];").unwrap();
+
+        for block in self.blocks.values() {
+            let label = block.instructions.iter()
+                .map(|address| format!("{:04} {}\\l", address, self.instructions[address]))
+                .collect::<String>();
+            writeln!(dot, "    b{} [label=\"{}\"];", block.start, label).unwrap();
+        }
+
+        for block in self.blocks.values() {
+            for successor in block.successors.iter() {
+                writeln!(dot, "    b{} -> b{};", block.start, successor).unwrap();
+            }
+            if block.indirect {
+                writeln!(dot, "    b{} -> indirect [style=dashed];", block.start).unwrap();
+            }
+        }
+
+        if self.blocks.values().any(|block| block.indirect) {
+            writeln!(dot, "    indirect [label=\"?\", shape=circle];").unwrap();
+        }
+        writeln!(dot, "}}").unwrap();
+
+        dot
+    }
+
+    /// Reachable instructions that can execute after the one at `site`, following every jump
+    /// that could be taken. An indirect jump could go anywhere, so it reaches everything.
+    fn reachable_after(&self, site: usize, successors: &BTreeMap<usize, Vec<usize>>) -> BTreeSet<usize> {
+        let mut seen = BTreeSet::new();
+        let mut queue = successors[&site].iter().copied().collect::<VecDeque<usize>>();
+
+        while let Some(address) = queue.pop_front() {
+            if !seen.insert(address) {
+                continue;
+            }
+            if self.indirect_jumps.contains(&address) {
+                return self.instructions.keys().copied().collect();
+            }
+            if let Some(next) = successors.get(&address) {
+                queue.extend(next.iter().copied());
+            }
+        }
+
+        seen
+    }
+}
+
+/// Analyse the memory of a program
+pub fn analyze(program: &Program) -> Analysis {
+    let image = program.data.iter().copied().collect::<Vec<i64>>();
+    let mut analysis = Analysis {
+        instructions: BTreeMap::new(),
+        blocks: BTreeMap::new(),
+        written: BTreeMap::new(),
+        relative_writes: BTreeSet::new(),
+        indirect_jumps: BTreeSet::new(),
+        inputs: BTreeSet::new(),
+        outputs: BTreeSet::new(),
+        invalid: BTreeSet::new(),
+        modifications: Vec::new(),
+    };
+
+    // Where control can go after each reachable instruction
+    let mut successors = BTreeMap::new();
+    let mut leaders = BTreeSet::new();
+    leaders.insert(0);
+
+    let mut queue = VecDeque::new();
+    queue.push_back(0);
+    while let Some(address) = queue.pop_front() {
+        if analysis.instructions.contains_key(&address) || analysis.invalid.contains(&address) {
+            continue;
+        }
+
+        let line = match decode_at(&image, address, &program.instruction_set) {
+            Some(line) => line,
+            None => {
+                analysis.invalid.insert(address);
+                continue;
+            },
+        };
+        let (opcode, operands) = match &line {
+            Line::Code { instruction, operands, .. } => (instruction.opcode, operands.clone()),
+            Line::Data { .. } => unreachable!("decode only returns code"),
+        };
+
+        for n in (0..opcode.arity()).filter(|n| opcode.writes(*n)) {
+            match operands[n].mode {
+                Mode::Position if operands[n].value >= 0 => {
+                    analysis.written.entry(operands[n].value as usize).or_default().insert(address);
+                },
+                // A negative address faults when it runs, so it writes nothing
+                Mode::Position => (),
+                _ => {
+                    analysis.relative_writes.insert(address);
+                },
+            }
+        }
+        match opcode {
+            OpCode::Input => { analysis.inputs.insert(address); },
+            OpCode::Output => { analysis.outputs.insert(address); },
+            _ => (),
+        }
+
+        let next = jumps(address, opcode, &operands, &mut analysis.indirect_jumps);
+        if opcode == OpCode::JumpIfTrue || opcode == OpCode::JumpIfFalse {
+            leaders.extend(next.iter().copied());
+        }
+        queue.extend(next.iter().copied());
+        successors.insert(address, next);
+        analysis.instructions.insert(address, line);
+    }
+
+    // Instructions with more than one way in start blocks too
+    let mut predecessors = BTreeMap::<usize, usize>::new();
+    for next in successors.values() {
+        for address in next {
+            *predecessors.entry(*address).or_default() += 1;
+        }
+    }
+    leaders.extend(predecessors.iter().filter(|(_, count)| **count > 1).map(|(address, _)| *address));
+
+    let mut blocks = BTreeMap::new();
+    for leader in leaders.iter().filter(|leader| analysis.instructions.contains_key(leader)) {
+        let mut block = Block { start: *leader, instructions: vec![*leader], successors: Vec::new(), indirect: false };
+        let mut address = *leader;
+
+        loop {
+            let next = &successors[&address];
+            let falls_through = next.len() == 1
+                && !analysis.indirect_jumps.contains(&address)
+                && !leaders.contains(&next[0])
+                && analysis.instructions.contains_key(&next[0]);
+
+            if !falls_through {
+                block.successors = next.iter()
+                    .copied()
+                    .filter(|next| analysis.instructions.contains_key(next))
+                    .collect();
+                block.indirect = analysis.indirect_jumps.contains(&address);
+                break;
+            }
+
+            address = next[0];
+            block.instructions.push(address);
+        }
+
+        blocks.insert(*leader, block);
+    }
+    analysis.blocks = blocks;
+
+    let mut after = BTreeMap::new();
+    for (address, sites) in analysis.written.iter() {
+        // A reachable address that isn't a valid instruction yet may well be one once written
+        let mut covering = analysis.instructions.range(address.saturating_sub(3)..=*address)
+            .filter(|(start, line)| *start + line.words().len() > *address)
+            .map(|(start, _)| *start)
+            .collect::<Vec<usize>>();
+        if analysis.invalid.contains(address) {
+            covering.push(*address);
+        }
+
+        for site in sites {
+            let reachable = after.entry(*site).or_insert_with(|| analysis.reachable_after(*site, &successors));
+            for instruction in covering.iter().filter(|instruction| reachable.contains(instruction)) {
+                analysis.modifications.push(Modification { site: *site, address: *address, instruction: *instruction });
+            }
+        }
+    }
+    analysis.modifications.sort_by_key(|modification| (modification.site, modification.address));
+
+    analysis
+}
+
+fn decode_at(image: &[i64], address: usize, instruction_set: &InstructionSet) -> Option<Line> {
+    if address < image.len() {
+        decode(image, address, instruction_set)
+    } else {
+        None
+    }
+}
+
+/// Where control can go after an instruction, noting jumps to targets only known at run time
+fn jumps(address: usize, opcode: OpCode, operands: &[Operand], indirect: &mut BTreeSet<usize>) -> Vec<usize> {
+    let next = address + opcode.size();
+
+    match opcode {
+        OpCode::Halt => Vec::new(),
+        OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+            let (condition, target) = (operands[0], operands[1]);
+            let jumps_if = opcode == OpCode::JumpIfTrue;
+
+            let (may_jump, may_fall_through) = match condition.mode {
+                Mode::Immediate => ((condition.value != 0) == jumps_if, (condition.value != 0) != jumps_if),
+                _ => (true, true),
+            };
+
+            let mut successors = Vec::new();
+            if may_jump {
+                match target.mode {
+                    Mode::Immediate if target.value >= 0 => successors.push(target.value as usize),
+                    Mode::Immediate => (),
+                    _ => { indirect.insert(address); },
+                }
+            }
+            if may_fall_through {
+                successors.push(next);
+            }
+
+            successors
+        },
+        // A custom instruction's handler can send control anywhere
+        OpCode::Custom(_) => {
+            indirect.insert(address);
+            vec![next]
+        },
+        _ => vec![next],
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |addresses: &mut dyn Iterator<Item = &usize>| {
+            let addresses = addresses.map(|address| format!("{:04}", address)).collect::<Vec<String>>();
+            if addresses.is_empty() { "none".to_string() } else { addresses.join(", ") }
+        };
+
+        writeln!(f, "Reachable instructions: {} in {} blocks", self.instructions.len(), self.blocks.len())?;
+        writeln!(f, "Inputs at: {}", list(&mut self.inputs.iter()))?;
+        writeln!(f, "Outputs at: {}", list(&mut self.outputs.iter()))?;
+        writeln!(f, "Written: {}", list(&mut self.written.keys()))?;
+        writeln!(f, "Written in relative mode at: {}", list(&mut self.relative_writes.iter()))?;
+        writeln!(f, "Indirect jumps at: {}", list(&mut self.indirect_jumps.iter()))?;
+        writeln!(f, "Invalid instructions at: {}", list(&mut self.invalid.iter()))?;
+
+        match (self.self_modifying(), self.may_self_modify()) {
+            (true, _) => writeln!(f, "Self-modifying: yes")?,
+            (false, true) => writeln!(f, "Self-modifying: maybe, through relative writes")?,
+            (false, false) => writeln!(f, "Self-modifying: no")?,
+        }
+        for modification in self.modifications.iter() {
+            writeln!(f, "    {:04} writes {:04}, in the instruction at {:04}",
+                modification.site, modification.address, modification.instruction)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn self_modification() -> crate::Result<()> {
+    // The add at 0 turns the add at 4 into a mul
+    let analysis = analyze(&"1,1,1,4,1,5,6,0,99".parse::<Program>()?);
+    assert!(analysis.self_modifying());
+    assert_eq!(analysis.modifications, vec![Modification { site: 0, address: 4, instruction: 4 }]);
+
+    // Each instruction of day 2 writes only to itself, or to code that already ran
+    let analysis = analyze(&std::fs::read_to_string("input/day02.txt")?.parse::<Program>()?);
+    assert!(!analysis.may_self_modify());
+    assert!(analysis.written.contains_key(&0));
+
+    // Day 5 writes the instruction it runs next from its input
+    let analysis = analyze(&std::fs::read_to_string("input/day05.txt")?.parse::<Program>()?);
+    assert_eq!(analysis.invalid, [6].iter().copied().collect());
+    assert_eq!(analysis.modifications, vec![Modification { site: 2, address: 6, instruction: 6 }]);
+
+    // A write to a negative address never happens
+    let analysis = analyze(&"1101,1,1,-1,99".parse::<Program>()?);
+    assert!(analysis.written.is_empty());
+    assert!(!analysis.may_self_modify());
+
+    Ok(())
+}
+
+#[test]
+fn control_flow() -> crate::Result<()> {
+    let program = crate::intcode::asm::assemble("
+        loop:
+            in  x
+            jf  x, #done
+            out x
+            jt  #1, #loop
+        done:
+            jt  #1, @0
+        x: db 0
+    ")?;
+    let analysis = analyze(&program);
+
+    assert_eq!(analysis.inputs, [0].iter().copied().collect());
+    assert_eq!(analysis.outputs, [5].iter().copied().collect());
+    assert_eq!(analysis.indirect_jumps, [10].iter().copied().collect());
+    assert_eq!(analysis.blocks.keys().copied().collect::<Vec<usize>>(), vec![0, 5, 10]);
+    assert_eq!(analysis.blocks[&0].successors, vec![10, 5]);
+    assert_eq!(analysis.blocks[&5].successors, vec![0]);
+    assert!(analysis.blocks[&10].indirect);
+    assert!(!analysis.self_modifying());
+
+    assert_eq!(analysis.dot(), [
+        "digraph intcode {",
+        "    node [shape=box, fontname=monospace];",
+        "    b0 [label=\"0000 in  13\\l0002 jf  13, #10\\l\"];",
+        "    b5 [label=\"0005 out 13\\l0007 jt  #1, #0\\l\"];",
+        "    b10 [label=\"0010 jt  #1, @0\\l\"];",
+        "    b0 -> b10;",
+        "    b0 -> b5;",
+        "    b5 -> b0;",
+        "    b10 -> indirect [style=dashed];",
+        "    indirect [label=\"?\", shape=circle];",
+        "}",
+        "",
+    ].join("\n"));
+
+    Ok(())
+}
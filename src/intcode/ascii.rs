@@ -0,0 +1,109 @@
+use crate::intcode::{IntcodeError, Program, State};
+
+/// Talks to a program in text. Input goes in as newline-terminated lines of ASCII code points,
+/// and output comes back as lines, with any values outside ASCII set aside as answers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsciiIo {
+    pub program: Program,
+    lines: Vec<String>,
+    /// The line being output, up to its newline
+    partial: String,
+    answers: Vec<i64>,
+}
+
+impl AsciiIo {
+    pub fn new(program: Program) -> Self {
+        AsciiIo {
+            program,
+            lines: Vec::new(),
+            partial: String::new(),
+            answers: Vec::new(),
+        }
+    }
+
+    /// Queue a line of input, adding its newline
+    pub fn send_line(&mut self, line: &str) {
+        self.send(line);
+        self.program.push_input(i64::from(b'\n'));
+    }
+
+    /// Queue text as input, exactly as given
+    pub fn send(&mut self, text: &str) {
+        self.program.extend_input(text.chars().map(|c| c as i64));
+    }
+
+    /// Run until the program needs more input or halts, collecting its output
+    pub fn run(&mut self) -> Result<State, IntcodeError> {
+        loop {
+            match self.program.run()? {
+                State::Output(value) => self.receive(value),
+                state => return Ok(state),
+            }
+        }
+    }
+
+    fn receive(&mut self, value: i64) {
+        match value {
+            10 => self.lines.push(std::mem::take(&mut self.partial)),
+            0..=127 => self.partial.push(value as u8 as char),
+            _ => self.answers.push(value),
+        }
+    }
+
+    /// Complete lines of output so far
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Output since the last newline, such as a prompt
+    pub fn partial(&self) -> &str {
+        &self.partial
+    }
+
+    /// Take the complete lines of output so far, leaving none
+    pub fn take_lines(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.lines)
+    }
+
+    /// Every output value that wasn't ASCII
+    pub fn answers(&self) -> &[i64] {
+        &self.answers
+    }
+
+    /// The last output value that wasn't ASCII
+    pub fn answer(&self) -> Option<i64> {
+        self.answers.last().copied()
+    }
+}
+
+#[test]
+fn lines_and_answers() -> crate::Result<()> {
+    // Echoes one line of input back after a prompt, then outputs 1000
+    let program = crate::intcode::asm::assemble("
+            out #62
+            out #10
+        loop:
+            in  char
+            out char
+            eq  char, #10, flag
+            jf  flag, #loop
+            out #1000
+            out #63
+            hlt
+        char: db 0
+        flag: db 0
+    ")?;
+
+    let mut ascii = AsciiIo::new(program);
+    assert_eq!(ascii.run()?, State::NeedsInput);
+    assert_eq!(ascii.lines(), [">"]);
+
+    ascii.send_line("Hello, World!");
+    assert_eq!(ascii.run()?, State::Halted);
+    assert_eq!(ascii.take_lines(), vec![">", "Hello, World!"]);
+    assert_eq!(ascii.partial(), "?");
+    assert_eq!(ascii.answer(), Some(1000));
+    assert!(ascii.lines().is_empty());
+
+    Ok(())
+}
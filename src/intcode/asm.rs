@@ -0,0 +1,316 @@
+//! Assembler for a small Intcode assembly language, the same one the disassembler prints:
+//!
+//! ```text
+//! ; Echo inputs until a zero comes in
+//! loop:
+//!     in  value
+//!     jf  value, #end       ; `#` marks an immediate operand, `@` a relative one
+//!     out value
+//!     jt  #1, #loop
+//! end:
+//!     hlt
+//! value:
+//!     db  0
+//! ```
+//!
+//! Operands are numbers or label names, optionally offset like `loop+2`.
+
+use crate::intcode::{Instruction, Mode, OpCode, Program};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// A mistake in assembly source, located by its 1-based line number
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum AsmError {
+    UnknownMnemonic { line: usize, mnemonic: String },
+    WrongOperandCount { line: usize, expected: usize, found: usize },
+    InvalidOperand { line: usize, operand: String },
+    ImmediateWrite { line: usize },
+    DuplicateLabel { line: usize, label: String },
+    UndefinedLabel { line: usize, label: String },
+}
+
+impl AsmError {
+    pub fn line(&self) -> usize {
+        match *self {
+            AsmError::UnknownMnemonic { line, .. }
+            | AsmError::WrongOperandCount { line, .. }
+            | AsmError::InvalidOperand { line, .. }
+            | AsmError::ImmediateWrite { line }
+            | AsmError::DuplicateLabel { line, .. }
+            | AsmError::UndefinedLabel { line, .. } => line,
+        }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsmError::UnknownMnemonic { mnemonic, .. } => write!(f, "Unknown mnemonic '{}'", mnemonic)?,
+            AsmError::WrongOperandCount { expected, found, .. } => {
+                write!(f, "Expected {} operands, found {}", expected, found)?
+            },
+            AsmError::InvalidOperand { operand, .. } => write!(f, "Invalid operand '{}'", operand)?,
+            AsmError::ImmediateWrite { .. } => write!(f, "Write to an immediate operand")?,
+            AsmError::DuplicateLabel { label, .. } => write!(f, "Duplicate label '{}'", label)?,
+            AsmError::UndefinedLabel { label, .. } => write!(f, "Undefined label '{}'", label)?,
+        }
+
+        write!(f, " on line {}", self.line())
+    }
+}
+
+impl Error for AsmError {}
+
+/// A number, or a label plus an offset, to be resolved once every label's address is known
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Value {
+    Number(i64),
+    Label(String, i64),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Operand {
+    mode: Mode,
+    value: Value,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Item {
+    Code { opcode: OpCode, operands: Vec<Operand> },
+    Data(Vec<Value>),
+}
+
+/// Assemble source text into a program
+pub fn assemble(source: &str) -> Result<Program, AsmError> {
+    let mut labels = HashMap::new();
+    let mut items = Vec::new();
+    let mut address = 0;
+
+    for (i, text) in source.lines().enumerate() {
+        let line = i + 1;
+        let mut text = text.split(';').next().unwrap_or("").trim();
+
+        while let Some((label, rest)) = split_label(text) {
+            if labels.insert(label.to_string(), address).is_some() {
+                return Err(AsmError::DuplicateLabel { line, label: label.to_string() });
+            }
+            text = rest;
+        }
+
+        if text.is_empty() {
+            continue;
+        }
+
+        let item = parse_item(line, text)?;
+        address += match &item {
+            Item::Code { opcode, .. } => opcode.size(),
+            Item::Data(values) => values.len(),
+        };
+        items.push((line, item));
+    }
+
+    let mut data = Vec::with_capacity(address);
+    for (line, item) in items {
+        match item {
+            Item::Code { opcode, operands } => {
+                let mut modes = [Mode::Position; 3];
+                for (mode, operand) in modes.iter_mut().zip(operands.iter()) {
+                    *mode = operand.mode;
+                }
+
+                data.push(Instruction::new(opcode, modes).word);
+                for operand in operands {
+                    data.push(resolve(line, &operand.value, &labels)?);
+                }
+            },
+            Item::Data(values) => {
+                for value in values {
+                    data.push(resolve(line, &value, &labels)?);
+                }
+            },
+        }
+    }
+
+    Ok(Program::new(data))
+}
+
+/// Split a leading `label:` off a line
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let colon = text.find(':')?;
+    let label = &text[..colon];
+
+    if is_label(label) {
+        Some((label, text[colon + 1..].trim()))
+    } else {
+        None
+    }
+}
+
+fn is_label(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        },
+        _ => false,
+    }
+}
+
+fn parse_item(line: usize, text: &str) -> Result<Item, AsmError> {
+    let (mnemonic, rest) = match text.find(char::is_whitespace) {
+        Some(space) => (&text[..space], text[space..].trim()),
+        None => (text, ""),
+    };
+
+    let operands = if rest.is_empty() {
+        Vec::new()
+    } else {
+        rest.split(',').map(str::trim).collect::<Vec<&str>>()
+    };
+
+    if mnemonic == "db" {
+        return operands.iter()
+            .map(|operand| parse_value(line, operand))
+            .collect::<Result<Vec<Value>, AsmError>>()
+            .map(Item::Data);
+    }
+
+    let opcode = OpCode::from_mnemonic(mnemonic)
+        .ok_or_else(|| AsmError::UnknownMnemonic { line, mnemonic: mnemonic.to_string() })?;
+
+    if operands.len() != opcode.arity() {
+        return Err(AsmError::WrongOperandCount { line, expected: opcode.arity(), found: operands.len() });
+    }
+
+    let operands = operands.iter()
+        .map(|operand| parse_operand(line, operand))
+        .collect::<Result<Vec<Operand>, AsmError>>()?;
+
+    if let Some(n) = opcode.write_param() {
+        if operands[n].mode == Mode::Immediate {
+            return Err(AsmError::ImmediateWrite { line });
+        }
+    }
+
+    Ok(Item::Code { opcode, operands })
+}
+
+fn parse_operand(line: usize, text: &str) -> Result<Operand, AsmError> {
+    let (mode, value) = if let Some(value) = text.strip_prefix(Mode::Immediate.sigil()) {
+        (Mode::Immediate, value)
+    } else if let Some(value) = text.strip_prefix(Mode::Relative.sigil()) {
+        (Mode::Relative, value)
+    } else {
+        (Mode::Position, text)
+    };
+
+    Ok(Operand { mode, value: parse_value(line, value)? })
+}
+
+fn parse_value(line: usize, text: &str) -> Result<Value, AsmError> {
+    let invalid = || AsmError::InvalidOperand { line, operand: text.to_string() };
+
+    if let Ok(number) = text.parse::<i64>() {
+        return Ok(Value::Number(number));
+    }
+
+    let (label, offset) = match text.find(['+', '-']) {
+        Some(sign) => {
+            let offset = text[sign..].trim_start_matches('+').parse::<i64>().map_err(|_| invalid())?;
+            (text[..sign].trim(), offset)
+        },
+        None => (text, 0),
+    };
+
+    if is_label(label) {
+        Ok(Value::Label(label.to_string(), offset))
+    } else {
+        Err(invalid())
+    }
+}
+
+fn resolve(line: usize, value: &Value, labels: &HashMap<String, usize>) -> Result<i64, AsmError> {
+    match value {
+        Value::Number(number) => Ok(*number),
+        Value::Label(label, offset) => {
+            let address = labels.get(label).ok_or_else(|| AsmError::UndefinedLabel { line, label: label.clone() })?;
+            (*address as i64).checked_add(*offset)
+                .ok_or_else(|| AsmError::InvalidOperand { line, operand: format!("{}{:+}", label, offset) })
+        },
+    }
+}
+
+#[test]
+fn first_steps() -> crate::Result<()> {
+    let program = assemble("
+        add 9, 10, 3
+        mul 3, 11, 0
+        hlt
+        db 30, 40, 50
+    ")?;
+
+    assert_eq!(program, "1,9,10,3,2,3,11,0,99,30,40,50".parse::<Program>()?);
+
+    Ok(())
+}
+
+#[test]
+fn echo_until_zero() -> crate::Result<()> {
+    let mut program = assemble("
+        ; Echo inputs until a zero comes in
+        loop:
+            in  value
+            jf  value, #end       ; `#` marks an immediate operand, `@` a relative one
+            out value
+            jt  #1, #loop
+        end: hlt
+        value: db 0
+    ")?;
+
+    program.extend_input(vec![3, -4, 0]);
+    assert_eq!(program.run_to_halt()?, vec![3, -4]);
+
+    Ok(())
+}
+
+#[test]
+fn relative_and_offsets() -> crate::Result<()> {
+    let mut program = assemble("
+        arb #stack
+        add #3, #4, @1
+        out stack+1
+        hlt
+        stack: db 0, 0
+    ")?;
+
+    assert_eq!(program.run_to_halt()?, vec![7]);
+
+    Ok(())
+}
+
+#[test]
+fn errors() {
+    assert_eq!(assemble("nop").unwrap_err(), AsmError::UnknownMnemonic { line: 1, mnemonic: "nop".to_string() });
+    assert_eq!(assemble("\nadd 1, 2").unwrap_err(), AsmError::WrongOperandCount { line: 2, expected: 3, found: 2 });
+    assert_eq!(assemble("out 1x").unwrap_err(), AsmError::InvalidOperand { line: 1, operand: "1x".to_string() });
+    assert_eq!(assemble("in #4").unwrap_err(), AsmError::ImmediateWrite { line: 1 });
+    assert_eq!(assemble("a: a: hlt").unwrap_err(), AsmError::DuplicateLabel { line: 1, label: "a".to_string() });
+    assert_eq!(assemble("out b").unwrap_err(), AsmError::UndefinedLabel { line: 1, label: "b".to_string() });
+    assert_eq!(assemble("out a+9223372036854775807\na: db 0").unwrap_err(),
+        AsmError::InvalidOperand { line: 1, operand: "a+9223372036854775807".to_string() });
+}
+
+#[test]
+fn disassembly_round_trip() -> crate::Result<()> {
+    for day in [2, 5, 9].iter() {
+        let input = std::fs::read_to_string(format!("input/day{:02}.txt", day))?;
+        let program = input.parse::<Program>()?;
+        let listing = crate::intcode::disasm::disassemble(&program).to_string();
+
+        assert_eq!(assemble(&listing)?, program);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,199 @@
+use crate::intcode::{IntcodeError, Observer, Program, State};
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Instructions to execute between looks at the clock and the cancellation token
+const CHECK_INTERVAL: usize = 1024;
+
+/// Lets another thread stop a run. Clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Why a run stopped before the program did
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Exhausted {
+    Deadline,
+    Cancelled,
+    /// The program's `step_limit` was reached
+    Steps,
+}
+
+impl fmt::Display for Exhausted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Exhausted::Deadline => write!(f, "Ran out of time"),
+            Exhausted::Cancelled => write!(f, "Cancelled"),
+            Exhausted::Steps => write!(f, "Ran out of steps"),
+        }
+    }
+}
+
+impl Error for Exhausted {}
+
+/// How a run within a budget ended
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Outcome {
+    /// The program stopped by itself, just as `run` would have
+    Stopped(State),
+    /// The budget, or the program's step limit, ran out first
+    Exhausted(Exhausted),
+}
+
+/// How long one run may go on: a time to stop by, and a token to stop on. Both are only checked
+/// every so often, so a run can overshoot either by a few microseconds. To limit the number of
+/// instructions instead, set the program's `step_limit`: a run within a budget reports reaching it
+/// as `Exhausted::Steps`.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    pub deadline: Option<Instant>,
+    pub cancel: Option<CancelToken>,
+}
+
+impl Budget {
+    /// No limits at all
+    pub fn unlimited() -> Self {
+        Budget::default()
+    }
+
+    /// Stop once this much time has passed from now. A timeout too long to count to never stops.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.deadline = Instant::now().checked_add(timeout);
+        self
+    }
+
+    pub fn cancel_with(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Why a run that has executed `steps` instructions should stop, if it should
+    fn exhausted(&self, steps: usize) -> Option<Exhausted> {
+        if steps % CHECK_INTERVAL == 0 {
+            if self.cancel.as_ref().map_or(false, CancelToken::is_cancelled) {
+                return Some(Exhausted::Cancelled);
+            }
+            if self.deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+                return Some(Exhausted::Deadline);
+            }
+        }
+
+        None
+    }
+}
+
+impl Program {
+    /// Run like `run`, but give up with `Outcome::Exhausted` once the budget runs out. The
+    /// machine is left just as it was before the instruction it didn't execute, so it can be
+    /// inspected, or resumed with another run.
+    pub fn run_within(&mut self, budget: &Budget) -> Result<Outcome, IntcodeError> {
+        self.run_within_with(&mut (), budget)
+    }
+
+    /// Run within a budget, reporting each executed instruction to an observer
+    pub fn run_within_with<O: Observer>(&mut self, observer: &mut O, budget: &Budget) -> Result<Outcome, IntcodeError> {
+        let start = self.steps;
+
+        loop {
+            if let Some(exhausted) = budget.exhausted(self.steps - start) {
+                return Ok(Outcome::Exhausted(exhausted));
+            }
+            if self.step_limit.map_or(false, |limit| self.steps >= limit) {
+                return Ok(Outcome::Exhausted(Exhausted::Steps));
+            }
+
+            if let Some(state) = self.step_with(observer)? {
+                return Ok(Outcome::Stopped(state));
+            }
+        }
+    }
+
+    /// Run to halt like `run_to_halt_with`, within a budget. Gives the output, or why the budget
+    /// ran out first.
+    pub fn run_to_halt_within_with<O: Observer>(&mut self, observer: &mut O, budget: &Budget) -> Result<Result<Vec<i64>, Exhausted>, IntcodeError> {
+        let mut output = Vec::new();
+
+        loop {
+            match self.run_within_with(observer, budget)? {
+                Outcome::Stopped(State::Output(value)) => output.push(value),
+                Outcome::Stopped(State::Halted) => return Ok(Ok(output)),
+                Outcome::Stopped(State::NeedsInput) => return Err(self.missing_input()),
+                Outcome::Exhausted(exhausted) => return Ok(Err(exhausted)),
+            }
+        }
+    }
+}
+
+#[test]
+fn step_limit_within_budget() -> crate::Result<()> {
+    // Counts up forever, outputting each number
+    let mut program = crate::intcode::asm::assemble("
+        loop:
+            add n, #1, n
+            out n
+            jt  #1, #loop
+        n: db 0
+    ")?;
+
+    let budget = Budget::unlimited().timeout(Duration::from_secs(60));
+    assert_eq!(program.run_within(&budget)?, Outcome::Stopped(State::Output(1)));
+    assert_eq!(program.run_within(&budget)?, Outcome::Stopped(State::Output(2)));
+
+    // Three instructions a loop, so this stops right before the third out
+    program.step_limit = Some(program.steps + 2);
+    assert_eq!(program.run_within(&budget)?, Outcome::Exhausted(Exhausted::Steps));
+    assert_eq!((program.head, program.steps), (4, 7));
+    program.step_limit = None;
+    assert_eq!(program.run()?, State::Output(3));
+
+    // Running to halt stops the same way, with the output so far dropped
+    program.step_limit = Some(program.steps + 2);
+    assert_eq!(program.run_to_halt_within_with(&mut (), &budget)?, Err(Exhausted::Steps));
+    program.step_limit = None;
+    let mut halts = "104,1,104,2,99".parse::<Program>()?;
+    assert_eq!(halts.run_to_halt_within_with(&mut (), &budget)?, Ok(vec![1, 2]));
+    halts.head = 0;
+    halts.data[0] = 3;
+    assert_eq!(halts.run_to_halt_within_with(&mut (), &budget), Err(IntcodeError::MissingInput { ip: 0, instruction: 3 }));
+
+    Ok(())
+}
+
+#[test]
+fn deadline_and_cancellation() -> crate::Result<()> {
+    let forever = "1105,1,0".parse::<Program>()?;
+
+    let mut program = forever.clone();
+    let budget = Budget::unlimited().timeout(Duration::from_millis(20));
+    assert_eq!(program.run_within(&budget)?, Outcome::Exhausted(Exhausted::Deadline));
+    assert!(Instant::now() >= budget.deadline.unwrap());
+
+    let token = CancelToken::new();
+    let budget = Budget::unlimited().cancel_with(token.clone());
+    let run = std::thread::spawn(move || {
+        let mut program = forever;
+        program.run_within(&budget)
+    });
+    token.cancel();
+    assert_eq!(run.join().unwrap(), Ok(Outcome::Exhausted(Exhausted::Cancelled)));
+
+    Ok(())
+}
@@ -0,0 +1,345 @@
+use crate::intcode::{checked_address, Executed, Instruction, IntcodeError, Memory, Mode, Observer, OpCode, Program, State};
+use crate::intcode::disasm::{disassemble, Line};
+use std::fmt;
+
+/// A parameter, resolved through its mode ahead of time as far as it can be
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Operand {
+    Position(usize),
+    Immediate(i64),
+    Relative(i64),
+}
+
+impl Operand {
+    #[inline(always)]
+    fn address(self, program: &Program, ip: usize, word: i64) -> Result<usize, IntcodeError> {
+        match self {
+            Operand::Position(address) => Ok(address),
+            Operand::Relative(offset) => match program.relative_base.checked_add(offset) {
+                Some(address) => checked_address(ip, word, address),
+                None => Err(IntcodeError::Overflow { ip, instruction: word }),
+            },
+            Operand::Immediate(_) => unreachable!("Immediate writes are left to the interpreter"),
+        }
+    }
+
+    #[inline(always)]
+    fn read(self, program: &Program, ip: usize, word: i64) -> Result<i64, IntcodeError> {
+        match self {
+            Operand::Immediate(value) => Ok(value),
+            _ => Ok(program.data[self.address(program, ip, word)?]),
+        }
+    }
+}
+
+/// What a compiled instruction did
+struct Step {
+    next: usize,
+    /// The state the machine stopped in, if it did
+    state: Option<State>,
+    written: Option<usize>,
+}
+
+type Op = Box<dyn Fn(&mut Program) -> Result<Step, IntcodeError> + Send + Sync>;
+
+/// A program image compiled ahead of time into one closure per instruction, with every
+/// instruction decoded and every parameter mode resolved, so running it skips decoding entirely.
+///
+/// The same compiled image can run many machines, each with its own memory and input. Running
+/// never trusts a closure whose instruction has changed since it was compiled, whether the program
+/// wrote to its own code or the caller changed memory before running it; those instructions go
+/// through the interpreter instead, as do jumps to anywhere the disassembler didn't find code.
+pub struct Compiled {
+    image: Memory,
+    /// The size and closure of the instruction at each address, if it decodes
+    ops: Vec<Option<(usize, Op)>>,
+    /// Which addresses have compiled code
+    compiled: Vec<bool>,
+    /// Which addresses are part of compiled code, so writing to them invalidates it
+    covered: Vec<bool>,
+}
+
+impl fmt::Debug for Compiled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Compiled")
+            .field("image", &self.image)
+            .field("instructions", &self.instructions())
+            .finish()
+    }
+}
+
+impl Compiled {
+    /// Compile the code found by disassembling a program
+    pub fn new(program: &Program) -> Self {
+        let image = program.data.iter().copied().collect::<Vec<i64>>();
+        let mut ops = image.iter().map(|_| None).collect::<Vec<Option<(usize, Op)>>>();
+        let mut covered = vec![false; image.len()];
+
+        for line in disassemble(program).lines {
+            if let Line::Code { address, .. } = line {
+                ops[address] = compile(&image, address);
+                if let Some((size, _)) = ops[address] {
+                    covered[address..address + size].iter_mut().for_each(|cell| *cell = true);
+                }
+            }
+        }
+        let compiled = ops.iter().map(Option::is_some).collect();
+
+        Compiled { image: program.data.clone(), ops, compiled, covered }
+    }
+
+    /// Number of addresses compiled
+    pub fn instructions(&self) -> usize {
+        self.compiled.iter().filter(|compiled| **compiled).count()
+    }
+
+    /// Run a machine with this compiled code. The program's memory is expected to start out much
+    /// like the image that was compiled, but it need not match.
+    pub fn runner(&self, program: Program) -> Runner<'_> {
+        let changed = program.data.differences(&self.image).collect::<Vec<usize>>();
+
+        let mut runner = Runner { compiled: self, program, valid: self.compiled.clone() };
+        for address in changed {
+            runner.invalidate(address);
+        }
+
+        runner
+    }
+}
+
+fn compile(image: &[i64], ip: usize) -> Option<(usize, Op)> {
+    let instruction = Instruction::decode(ip, image[ip]).ok()?;
+    let opcode = instruction.opcode;
+    let size = opcode.size();
+
+    if ip + size > image.len() {
+        return None;
+    }
+    if let Some(n) = opcode.write_param() {
+        if instruction.modes[n] == Mode::Immediate {
+            return None;
+        }
+    }
+
+    let mut operands = [Operand::Immediate(0); 3];
+    for (n, operand) in operands.iter_mut().enumerate().take(opcode.arity()) {
+        let value = image[ip + 1 + n];
+        *operand = match instruction.modes[n] {
+            Mode::Position => Operand::Position(checked_address(ip, instruction.word, value).ok()?),
+            Mode::Immediate => Operand::Immediate(value),
+            Mode::Relative => Operand::Relative(value),
+        };
+    }
+
+    let [a, b, _] = operands;
+    let word = instruction.word;
+    let next = ip + size;
+
+    let op: Op = match opcode {
+        OpCode::Add => arithmetic(ip, word, operands, i64::checked_add),
+        OpCode::Mul => arithmetic(ip, word, operands, i64::checked_mul),
+        OpCode::LessThan => arithmetic(ip, word, operands, |x, y| Some((x < y) as i64)),
+        OpCode::Equals => arithmetic(ip, word, operands, |x, y| Some((x == y) as i64)),
+        OpCode::Input => Box::new(move |program| {
+            if program.input.is_empty() {
+                return Ok(Step { next: ip, state: Some(State::NeedsInput), written: None });
+            }
+            let address = a.address(program, ip, word)?;
+            program.data[address] = program.input.pop_front().unwrap_or_default();
+            Ok(Step { next, state: None, written: Some(address) })
+        }),
+        OpCode::Output => Box::new(move |program| {
+            let value = a.read(program, ip, word)?;
+            Ok(Step { next, state: Some(State::Output(value)), written: None })
+        }),
+        OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+            let jump_if = opcode == OpCode::JumpIfTrue;
+            Box::new(move |program| {
+                let condition = a.read(program, ip, word)?;
+                let next = if (condition != 0) == jump_if {
+                    checked_address(ip, word, b.read(program, ip, word)?)?
+                } else {
+                    next
+                };
+                Ok(Step { next, state: None, written: None })
+            })
+        },
+        OpCode::AdjustBase => Box::new(move |program| {
+            program.relative_base = program.relative_base.checked_add(a.read(program, ip, word)?)
+                .ok_or(IntcodeError::Overflow { ip, instruction: word })?;
+            Ok(Step { next, state: None, written: None })
+        }),
+        OpCode::Halt => Box::new(move |_| Ok(Step { next: ip, state: Some(State::Halted), written: None })),
+        // Left to the interpreter, which knows the program's instruction set
+        OpCode::Custom(_) => return None,
+    };
+
+    Some((size, op))
+}
+
+fn arithmetic<F>(ip: usize, word: i64, [a, b, c]: [Operand; 3], f: F) -> Op
+where F: Fn(i64, i64) -> Option<i64> + Send + Sync + 'static
+{
+    let next = ip + 4;
+    Box::new(move |program| {
+        let value = f(a.read(program, ip, word)?, b.read(program, ip, word)?)
+            .ok_or(IntcodeError::Overflow { ip, instruction: word })?;
+        let address = c.address(program, ip, word)?;
+        program.data[address] = value;
+        Ok(Step { next, state: None, written: Some(address) })
+    })
+}
+
+/// A machine running compiled code, falling back to the interpreter for any instruction that
+/// isn't compiled or has changed since
+#[derive(Debug)]
+pub struct Runner<'a> {
+    compiled: &'a Compiled,
+    pub program: Program,
+    /// Whether the closure at each address still matches memory
+    valid: Vec<bool>,
+}
+
+impl Runner<'_> {
+    /// Execute one instruction, like `Program::step`
+    pub fn step(&mut self) -> Result<Option<State>, IntcodeError> {
+        let ip = self.program.head;
+
+        if !self.valid.get(ip).copied().unwrap_or(false) {
+            let mut written = Written(None);
+            let state = self.program.step_with(&mut written)?;
+            if let Some(address) = written.0 {
+                self.invalidate(address);
+            }
+            return Ok(state);
+        }
+
+        if let Some(limit) = self.program.step_limit {
+            if self.program.steps >= limit {
+                return Err(IntcodeError::StepLimitExceeded { ip, instruction: self.program.data[ip], limit });
+            }
+        }
+
+        let op = match &self.compiled.ops[ip] {
+            Some((_, op)) => op,
+            None => unreachable!("Valid address without compiled code"),
+        };
+        let step = op(&mut self.program)?;
+
+        if let Some(address) = step.written {
+            self.invalidate(address);
+        }
+        if step.state != Some(State::NeedsInput) && step.state != Some(State::Halted) {
+            self.program.head = step.next;
+            self.program.steps += 1;
+        }
+
+        Ok(step.state)
+    }
+
+    /// Step until the program outputs a value, needs input, or halts
+    pub fn run(&mut self) -> Result<State, IntcodeError> {
+        loop {
+            if let Some(state) = self.step()? {
+                return Ok(state);
+            }
+        }
+    }
+
+    /// Run until the program halts, collecting its output. Fails with
+    /// `IntcodeError::MissingInput` if it runs out of input on the way.
+    pub fn run_to_halt(&mut self) -> Result<Vec<i64>, IntcodeError> {
+        let mut output = Vec::new();
+
+        loop {
+            match self.run()? {
+                State::Output(value) => output.push(value),
+                State::Halted => return Ok(output),
+                State::NeedsInput => return Err(self.program.missing_input()),
+            }
+        }
+    }
+
+    pub fn push_input(&mut self, value: i64) {
+        self.program.push_input(value);
+    }
+
+    /// Stop trusting every instruction that covers a written address
+    fn invalidate(&mut self, address: usize) {
+        if !self.compiled.covered.get(address).copied().unwrap_or(false) {
+            return;
+        }
+
+        for ip in address.saturating_sub(3)..=address {
+            if let Some(valid) = self.valid.get_mut(ip) {
+                *valid = false;
+            }
+        }
+    }
+}
+
+/// Notes the address an interpreted instruction wrote to
+struct Written(Option<usize>);
+
+impl Observer for Written {
+    fn on_step(&mut self, executed: &Executed) {
+        self.0 = executed.written.map(|(address, _)| address);
+    }
+}
+
+#[test]
+fn same_as_interpreter() -> crate::Result<()> {
+    for (day, inputs) in [("05", vec![5]), ("09", vec![1]), ("09", vec![2])].iter() {
+        let mut program = std::fs::read_to_string(format!("input/day{}.txt", day))?.parse::<Program>()?;
+        program.extend_input(inputs.iter().copied());
+
+        let compiled = Compiled::new(&program);
+        let mut runner = compiled.runner(program.clone());
+        assert_eq!(runner.run_to_halt()?, program.run_to_halt()?);
+        assert_eq!(runner.program, program);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn self_modifying() -> crate::Result<()> {
+    // The add at 0 turns the add at 4 into a mul
+    let mut program = "1,1,1,4,1,5,6,0,99".parse::<Program>()?;
+    let compiled = Compiled::new(&program);
+
+    let mut runner = compiled.runner(program.clone());
+    runner.run_to_halt()?;
+    program.run_to_halt()?;
+    assert_eq!(runner.program.first(), 30);
+    assert_eq!(runner.program, program);
+
+    // Changing memory before running is just as safe. Now the add at 0 writes 11 to 4.
+    let mut changed = "1,1,1,4,1,5,6,0,99".parse::<Program>()?.change_values(5, 6);
+    let mut runner = compiled.runner(changed.clone());
+    assert_eq!(runner.run_to_halt(), Err(IntcodeError::UnknownOpCode { ip: 4, instruction: 11 }));
+    assert_eq!(changed.run_to_halt(), Err(IntcodeError::UnknownOpCode { ip: 4, instruction: 11 }));
+    assert_eq!(runner.program, changed);
+
+    Ok(())
+}
+
+#[test]
+fn errors() -> crate::Result<()> {
+    let program = "109,-5,204,0,99".parse::<Program>()?;
+    let compiled = Compiled::new(&program);
+    assert_eq!(compiled.instructions(), 3);
+    assert_eq!(
+        compiled.runner(program.clone()).run_to_halt(),
+        Err(IntcodeError::NegativeAddress { ip: 2, instruction: 204, address: -5 }),
+    );
+
+    let mut limited = program;
+    limited.step_limit = Some(1);
+    assert_eq!(
+        compiled.runner(limited).run_to_halt(),
+        Err(IntcodeError::StepLimitExceeded { ip: 2, instruction: 204, limit: 1 }),
+    );
+
+    Ok(())
+}
@@ -0,0 +1,368 @@
+use crate::intcode::disasm;
+use crate::intcode::{IntcodeError, OpCode, Program, State};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::{self, BufRead, Write};
+
+/// Cells per line of a memory dump
+const DUMP_WIDTH: usize = 8;
+/// Instructions a `continue` executes before giving up, unless it's given another number
+pub const CONTINUE_STEPS: usize = 10_000_000;
+
+const HELP: &str = "\
+Commands:
+    s, step [n]          Execute n instructions (default 1)
+    c, continue [n]      Run until a breakpoint, watchpoint, input request or halt, or for at
+                         most n instructions (default 10000000)
+    b, break <addr|op>   Break at an address, or before any instruction with an opcode mnemonic
+    d, delete <addr|op>  Remove a breakpoint
+    w, watch <addr>      Break when the value at an address changes
+    u, unwatch <addr>    Remove a watchpoint
+    i, input <values>    Queue input values
+    r, regs              Show the instruction pointer, relative base and step count
+    l, list [addr] [n]   Disassemble n instructions (default 8) from an address (default ip)
+    x, dump <addr> [n]   Hex dump n memory cells (default 8)
+    h, help              Show this message
+    q, quit              Leave the debugger";
+
+/// Why a `continue` stopped
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Stop {
+    Breakpoint(usize),
+    OpCode(OpCode),
+    Watchpoint { address: usize, old: i64, new: i64 },
+    NeedsInput,
+    Halted,
+    Error(IntcodeError),
+    /// The step budget of the `continue` ran out
+    Steps(usize),
+}
+
+/// An interactive wrapper around a `Program`
+#[derive(Debug, Clone)]
+pub struct Debugger {
+    pub program: Program,
+    breakpoints: BTreeSet<usize>,
+    opcode_breakpoints: HashSet<OpCode>,
+    /// Watched addresses, with the value last seen at each
+    watchpoints: BTreeMap<usize, i64>,
+    pub output: Vec<i64>,
+}
+
+impl Debugger {
+    pub fn new(program: Program) -> Self {
+        Debugger {
+            program,
+            breakpoints: BTreeSet::new(),
+            opcode_breakpoints: HashSet::new(),
+            watchpoints: BTreeMap::new(),
+            output: Vec::new(),
+        }
+    }
+
+    /// Read commands until `quit` or the end of input, prompting and reporting on `out`
+    pub fn repl<R: BufRead, W: Write>(&mut self, input: R, mut out: W) -> io::Result<()> {
+        writeln!(out, "{}", self.current())?;
+        write!(out, "{:04}> ", self.program.head)?;
+        out.flush()?;
+
+        for line in input.lines() {
+            if !self.execute(&line?, &mut out)? {
+                break;
+            }
+            write!(out, "{:04}> ", self.program.head)?;
+            out.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Execute one command line. Returns false once the user asks to quit.
+    pub fn execute<W: Write>(&mut self, line: &str, out: &mut W) -> io::Result<bool> {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => return Ok(true),
+        };
+        let args = words.collect::<Vec<&str>>();
+
+        match (command, args.as_slice()) {
+            ("s", _) | ("step", _) => {
+                let n = args.first().and_then(|n| n.parse::<usize>().ok()).unwrap_or(1);
+                for _ in 0..n {
+                    if let Some(stop) = self.step(out)? {
+                        writeln!(out, "{}", describe(&stop))?;
+                        break;
+                    }
+                }
+                writeln!(out, "{}", self.current())?;
+            },
+            ("c", _) | ("continue", _) => {
+                let steps = args.first().and_then(|n| n.parse::<usize>().ok()).unwrap_or(CONTINUE_STEPS);
+                let stop = self.resume(steps, out)?;
+                writeln!(out, "{}", describe(&stop))?;
+                writeln!(out, "{}", self.current())?;
+            },
+            ("b", [target]) | ("break", [target]) => {
+                match (target.parse::<usize>(), OpCode::from_mnemonic(target)) {
+                    (Ok(address), _) => { self.breakpoints.insert(address); },
+                    (_, Some(opcode)) => { self.opcode_breakpoints.insert(opcode); },
+                    _ => writeln!(out, "Not an address or opcode: {}", target)?,
+                }
+            },
+            ("d", [target]) | ("delete", [target]) => {
+                match (target.parse::<usize>(), OpCode::from_mnemonic(target)) {
+                    (Ok(address), _) => { self.breakpoints.remove(&address); },
+                    (_, Some(opcode)) => { self.opcode_breakpoints.remove(&opcode); },
+                    _ => writeln!(out, "Not an address or opcode: {}", target)?,
+                }
+            },
+            ("w", [address]) | ("watch", [address]) => match address.parse::<usize>() {
+                Ok(address) => { self.watchpoints.insert(address, self.program.data[address]); },
+                Err(_) => writeln!(out, "Not an address: {}", address)?,
+            },
+            ("u", [address]) | ("unwatch", [address]) => match address.parse::<usize>() {
+                Ok(address) => { self.watchpoints.remove(&address); },
+                Err(_) => writeln!(out, "Not an address: {}", address)?,
+            },
+            ("i", values) | ("input", values) => {
+                for value in values {
+                    match value.trim_end_matches(',').parse::<i64>() {
+                        Ok(value) => self.program.push_input(value),
+                        Err(_) => writeln!(out, "Not a number: {}", value)?,
+                    }
+                }
+            },
+            ("r", _) | ("regs", _) => {
+                writeln!(out, "ip: {}  rb: {}  steps: {}  input: {:?}",
+                    self.program.head, self.program.relative_base, self.program.steps, self.program.input)?;
+            },
+            ("l", _) | ("list", _) => {
+                let address = args.first().and_then(|a| a.parse::<usize>().ok()).unwrap_or(self.program.head);
+                let count = args.get(1).and_then(|n| n.parse::<usize>().ok()).unwrap_or(8);
+                self.list(address, count, out)?;
+            },
+            ("x", [address, rest @ ..]) | ("dump", [address, rest @ ..]) => match address.parse::<usize>() {
+                Ok(address) => {
+                    let count = rest.first().and_then(|n| n.parse::<usize>().ok()).unwrap_or(DUMP_WIDTH);
+                    self.dump(address, count, out)?;
+                },
+                Err(_) => writeln!(out, "Not an address: {}", address)?,
+            },
+            ("h", _) | ("help", _) => writeln!(out, "{}", HELP)?,
+            ("q", _) | ("quit", _) => return Ok(false),
+            _ => writeln!(out, "Unknown command: {} (try 'help')", line.trim())?,
+        }
+
+        Ok(true)
+    }
+
+    /// Run until something interesting happens, or for at most `steps` instructions. Always
+    /// executes at least one instruction, so continuing from a breakpoint moves past it.
+    pub fn resume<W: Write>(&mut self, steps: usize, out: &mut W) -> io::Result<Stop> {
+        if let Some(stop) = self.step(out)? {
+            return Ok(stop);
+        }
+
+        for _ in 1..steps {
+            let head = self.program.head;
+            if self.breakpoints.contains(&head) {
+                return Ok(Stop::Breakpoint(head));
+            }
+
+            if let Ok(instruction) = self.program.instruction_set.decode(head, self.program.data[head]) {
+                if self.opcode_breakpoints.contains(&instruction.opcode) {
+                    return Ok(Stop::OpCode(instruction.opcode));
+                }
+            }
+
+            if let Some(stop) = self.step(out)? {
+                return Ok(stop);
+            }
+        }
+
+        Ok(Stop::Steps(steps))
+    }
+
+    /// Execute one instruction, printing any output. Returns the reason to stop, if there is one.
+    fn step<W: Write>(&mut self, out: &mut W) -> io::Result<Option<Stop>> {
+        let stop = match self.program.step() {
+            Ok(None) => None,
+            Ok(Some(State::Output(value))) => {
+                writeln!(out, "Output: {}", value)?;
+                self.output.push(value);
+                None
+            },
+            Ok(Some(State::NeedsInput)) => Some(Stop::NeedsInput),
+            Ok(Some(State::Halted)) => Some(Stop::Halted),
+            Err(e) => Some(Stop::Error(e)),
+        };
+
+        if stop.is_some() {
+            return Ok(stop);
+        }
+
+        for (address, old) in self.watchpoints.iter_mut() {
+            let new = self.program.data[*address];
+            if new != *old {
+                let stop = Stop::Watchpoint { address: *address, old: *old, new };
+                *old = new;
+                return Ok(Some(stop));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// The instruction at the instruction pointer, disassembled
+    fn current(&self) -> String {
+        let image = self.program.data.iter().copied().collect::<Vec<i64>>();
+        match disasm::decode(&image, self.program.head, &self.program.instruction_set) {
+            Some(line) => format!("{:04}: {}", self.program.head, line),
+            None => format!("{:04}: ??? {}", self.program.head, self.program.data[self.program.head]),
+        }
+    }
+
+    fn list<W: Write>(&self, mut address: usize, count: usize, out: &mut W) -> io::Result<()> {
+        let image = self.program.data.iter().copied().collect::<Vec<i64>>();
+        for _ in 0..count {
+            let marker = if address == self.program.head { "=>" } else { "  " };
+            let breakpoint = if self.breakpoints.contains(&address) { "*" } else { " " };
+            match disasm::decode(&image, address, &self.program.instruction_set) {
+                Some(line) => {
+                    writeln!(out, "{}{}{:04}: {}", marker, breakpoint, address, line)?;
+                    match address.checked_add(line.words().len()) {
+                        Some(next) => address = next,
+                        None => break,
+                    }
+                },
+                None => {
+                    writeln!(out, "{}{}{:04}: db  {}", marker, breakpoint, address, self.program.data[address])?;
+                    match address.checked_add(1) {
+                        Some(next) => address = next,
+                        None => break,
+                    }
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn dump<W: Write>(&self, mut address: usize, count: usize, out: &mut W) -> io::Result<()> {
+        let mut remaining = count;
+        while remaining > 0 {
+            // Addresses past the end of memory read as zero, but the dump stops at the last usize
+            let cells = (0..remaining.min(DUMP_WIDTH))
+                .map_while(|n| address.checked_add(n))
+                .map(|a| format!("{:08x}", self.program.data[a]))
+                .collect::<Vec<String>>();
+            writeln!(out, "{:04}: {}", address, cells.join(" "))?;
+
+            remaining -= cells.len();
+            match address.checked_add(cells.len()) {
+                Some(next) => address = next,
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn describe(stop: &Stop) -> String {
+    match stop {
+        Stop::Breakpoint(address) => format!("Breakpoint at {:04}", address),
+        Stop::OpCode(opcode) => format!("Breakpoint on {}", opcode.mnemonic()),
+        Stop::Watchpoint { address, old, new } => format!("Watchpoint {:04}: {} -> {}", address, old, new),
+        Stop::NeedsInput => "Waiting for input".to_string(),
+        Stop::Halted => "Halted".to_string(),
+        Stop::Error(e) => format!("Error: {}", e),
+        Stop::Steps(steps) => format!("Stopped after {} steps", steps),
+    }
+}
+
+#[test]
+fn breakpoints_and_watchpoints() -> crate::Result<()> {
+    let program = crate::intcode::asm::assemble("
+        loop:
+            in  value
+            jf  value, #end
+            add value, total, total
+            jt  #1, #loop
+        end:
+            out total
+            hlt
+        value: db 0
+        total: db 0
+    ")?;
+    let mut debugger = Debugger::new(program);
+    let mut out = Vec::new();
+
+    debugger.execute("break 2", &mut out)?;
+    debugger.execute("watch 16", &mut out)?;
+    debugger.execute("input 3 4", &mut out)?;
+
+    assert_eq!(debugger.resume(CONTINUE_STEPS, &mut out)?, Stop::Breakpoint(2));
+    assert_eq!(debugger.resume(CONTINUE_STEPS, &mut out)?, Stop::Watchpoint { address: 16, old: 0, new: 3 });
+    assert_eq!(debugger.resume(CONTINUE_STEPS, &mut out)?, Stop::Breakpoint(2));
+
+    debugger.execute("delete 2", &mut out)?;
+    assert_eq!(debugger.resume(CONTINUE_STEPS, &mut out)?, Stop::Watchpoint { address: 16, old: 3, new: 7 });
+    assert_eq!(debugger.resume(CONTINUE_STEPS, &mut out)?, Stop::NeedsInput);
+
+    debugger.execute("input 0", &mut out)?;
+    debugger.execute("break out", &mut out)?;
+    assert_eq!(debugger.resume(CONTINUE_STEPS, &mut out)?, Stop::OpCode(OpCode::Output));
+    assert_eq!(debugger.program.head, 12);
+    assert_eq!(debugger.resume(CONTINUE_STEPS, &mut out)?, Stop::Halted);
+    assert_eq!(debugger.output, vec![7]);
+
+    Ok(())
+}
+
+#[test]
+fn repl_session() -> crate::Result<()> {
+    let program = "1101,2,3,5,99,0".parse::<Program>()?;
+    let mut debugger = Debugger::new(program);
+    let mut out = Vec::new();
+
+    debugger.repl("step\nregs\nx 4 2\nq\nstep\n".as_bytes(), &mut out)?;
+
+    assert_eq!(String::from_utf8(out)?, [
+        "0000: add #2, #3, 5",
+        "0000> 0004: hlt",
+        "0004> ip: 4  rb: 0  steps: 1  input: []",
+        "0004> 0004: 00000063 00000005",
+        "0004> ",
+    ].join("\n"));
+
+    Ok(())
+}
+
+#[test]
+fn past_the_end() -> crate::Result<()> {
+    let program = "1105,1,0".parse::<Program>()?;
+    let mut debugger = Debugger::new(program);
+    let mut out = Vec::new();
+
+    debugger.repl("l 1000 1\nx 18446744073709551615 2\nc 10\nq\n".as_bytes(), &mut out)?;
+
+    assert_eq!(String::from_utf8(out)?, [
+        "0000: jt  #1, #0",
+        "0000>    1000: db  0",
+        "0000> 18446744073709551615: 00000000",
+        "0000> Stopped after 10 steps",
+        "0000: jt  #1, #0",
+        "0000> ",
+    ].join("\n"));
+
+    // Jumping past the end of the image leaves nothing there to decode
+    let mut debugger = Debugger::new("1105,1,1000".parse::<Program>()?);
+    let mut out = Vec::new();
+    debugger.execute("step", &mut out)?;
+    assert_eq!(String::from_utf8(out)?, "1000: ??? 0\n");
+    let error = IntcodeError::UnknownOpCode { ip: 1000, instruction: 0 };
+    assert_eq!(debugger.resume(CONTINUE_STEPS, &mut Vec::new())?, Stop::Error(error));
+
+    Ok(())
+}
@@ -0,0 +1,383 @@
+use crate::intcode::{IntcodeError, Observer, Program, State};
+use crate::InputError;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+/// A peripheral a program talks to through its input and output instructions
+pub trait Device {
+    /// The value for the program's next input instruction, or `None` if the device has nothing to
+    /// give, which stops the run with `IntcodeError::MissingInput`
+    fn on_input(&mut self) -> Option<i64>;
+
+    /// A value the program output
+    fn on_output(&mut self, value: i64);
+
+    /// Called before each input and output with the number of instructions the program has
+    /// executed, for devices that keep time
+    fn at_step(&mut self, _steps: usize) {}
+}
+
+impl<D: Device + ?Sized> Device for &mut D {
+    fn on_input(&mut self) -> Option<i64> {
+        (**self).on_input()
+    }
+
+    fn on_output(&mut self, value: i64) {
+        (**self).on_output(value)
+    }
+
+    fn at_step(&mut self, steps: usize) {
+        (**self).at_step(steps)
+    }
+}
+
+impl Program {
+    /// Bind the program to a device and run it until it halts. Queued input is used up before the
+    /// device is asked for any.
+    pub fn run_device<D: Device>(&mut self, device: &mut D) -> Result<(), IntcodeError> {
+        self.run_device_with(&mut (), device)
+    }
+
+    /// Run with a device, reporting each executed instruction to an observer
+    pub fn run_device_with<O: Observer, D: Device>(&mut self, observer: &mut O, device: &mut D) -> Result<(), IntcodeError> {
+        loop {
+            let state = self.run_with(observer)?;
+            device.at_step(self.steps);
+            match state {
+                State::Output(value) => device.on_output(value),
+                State::NeedsInput => match device.on_input() {
+                    Some(value) => self.push_input(value),
+                    None => return Err(self.missing_input()),
+                },
+                State::Halted => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Gives a fixed list of input values and collects the output
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Fixed {
+    pub input: VecDeque<i64>,
+    pub output: Vec<i64>,
+}
+
+impl Fixed {
+    pub fn new<I: IntoIterator<Item = i64>>(input: I) -> Self {
+        Fixed { input: input.into_iter().collect(), output: Vec::new() }
+    }
+}
+
+impl Device for Fixed {
+    fn on_input(&mut self) -> Option<i64> {
+        self.input.pop_front()
+    }
+
+    fn on_output(&mut self, value: i64) {
+        self.output.push(value);
+    }
+}
+
+/// Reads input values from a reader, one per line, and writes output values to a writer, one per
+/// line. Normally that's stdin and stdout.
+#[derive(Debug)]
+pub struct Stdio<R: BufRead, W: Write> {
+    reader: R,
+    writer: W,
+    /// The first error reading, parsing or writing, after which nothing more is read or written
+    error: Option<io::Error>,
+}
+
+impl Stdio<io::StdinLock<'static>, io::Stdout> {
+    /// A device on the terminal
+    pub fn terminal() -> Self {
+        Stdio::new(io::stdin().lock(), io::stdout())
+    }
+}
+
+impl<R: BufRead, W: Write> Stdio<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Stdio { reader, writer, error: None }
+    }
+
+    /// Flush the output and give the writer back, or the first error the device ran into
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn read(&mut self) -> io::Result<Option<i64>> {
+        self.writer.flush()?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        line.trim().parse::<i64>()
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid input {:?}: {}", line.trim(), e)))
+    }
+}
+
+impl<R: BufRead, W: Write> Device for Stdio<R, W> {
+    fn on_input(&mut self) -> Option<i64> {
+        if self.error.is_some() {
+            return None;
+        }
+
+        self.read().unwrap_or_else(|e| {
+            self.error = Some(e);
+            None
+        })
+    }
+
+    fn on_output(&mut self, value: i64) {
+        if self.error.is_none() {
+            if let Err(e) = writeln!(self.writer, "{}", value) {
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+/// One value passing between a program and its device
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Signal {
+    Input(i64),
+    Output(i64),
+}
+
+/// A signal, and the number of instructions the program had executed when it passed
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Event {
+    pub step: usize,
+    pub signal: Signal,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.signal {
+            Signal::Input(value) => write!(f, "{} in {}", self.step, value),
+            Signal::Output(value) => write!(f, "{} out {}", self.step, value),
+        }
+    }
+}
+
+impl FromStr for Event {
+    type Err = InputError<String>;
+
+    /// An event as written by `Display`, like `1234 out 7`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InputError::new(s.to_string());
+        let words = s.split_whitespace().collect::<Vec<&str>>();
+        let (step, direction, value) = match words[..] {
+            [step, direction, value] => (step, direction, value),
+            _ => return Err(invalid()),
+        };
+
+        let step = step.parse().map_err(|_| invalid())?;
+        let value = value.parse().map_err(|_| invalid())?;
+        let signal = match direction {
+            "in" => Signal::Input(value),
+            "out" => Signal::Output(value),
+            _ => return Err(invalid()),
+        };
+
+        Ok(Event { step, signal })
+    }
+}
+
+/// Everything that passed between a program and its device, in order. As a device, a tape plays
+/// its input back and ignores output. Written out, a tape is one event per line, and reading one
+/// skips blank lines and `#` comments.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Tape {
+    pub events: Vec<Event>,
+    /// Where playback has got to
+    position: usize,
+}
+
+impl Tape {
+    pub fn new(events: Vec<Event>) -> Self {
+        Tape { events, position: 0 }
+    }
+
+    /// The values input, in order
+    pub fn inputs(&self) -> impl Iterator<Item = i64> + '_ {
+        self.events.iter().filter_map(|event| match event.signal {
+            Signal::Input(value) => Some(value),
+            Signal::Output(_) => None,
+        })
+    }
+
+    /// The values output, in order
+    pub fn outputs(&self) -> impl Iterator<Item = i64> + '_ {
+        self.events.iter().filter_map(|event| match event.signal {
+            Signal::Output(value) => Some(value),
+            Signal::Input(_) => None,
+        })
+    }
+}
+
+impl fmt::Display for Tape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Tape {
+    type Err = InputError<String>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::parse)
+            .collect::<Result<Vec<Event>, _>>()
+            .map(Tape::new)
+    }
+}
+
+impl Device for Tape {
+    fn on_input(&mut self) -> Option<i64> {
+        while let Some(event) = self.events.get(self.position) {
+            self.position += 1;
+            if let Signal::Input(value) = event.signal {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn on_output(&mut self, _value: i64) {}
+}
+
+/// Wraps a device, giving the input queued on it before asking the device for any. Input queued
+/// this way rather than on the program passes through the device, so a `Recorder` sees it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Queued<D: Device> {
+    pub input: VecDeque<i64>,
+    pub device: D,
+}
+
+impl<D: Device> Queued<D> {
+    pub fn new<I: IntoIterator<Item = i64>>(input: I, device: D) -> Self {
+        Queued { input: input.into_iter().collect(), device }
+    }
+}
+
+impl<D: Device> Device for Queued<D> {
+    fn on_input(&mut self) -> Option<i64> {
+        self.input.pop_front().or_else(|| self.device.on_input())
+    }
+
+    fn on_output(&mut self, value: i64) {
+        self.device.on_output(value);
+    }
+
+    fn at_step(&mut self, steps: usize) {
+        self.device.at_step(steps);
+    }
+}
+
+/// Wraps a device, recording everything that passes through it on a tape
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Recorder<D: Device> {
+    pub device: D,
+    pub tape: Tape,
+    /// Instructions executed as of the latest signal
+    step: usize,
+}
+
+impl<D: Device> Recorder<D> {
+    pub fn new(device: D) -> Self {
+        Recorder { device, tape: Tape::default(), step: 0 }
+    }
+
+    fn record(&mut self, signal: Signal) {
+        self.tape.events.push(Event { step: self.step, signal });
+    }
+}
+
+impl<D: Device> Device for Recorder<D> {
+    fn on_input(&mut self) -> Option<i64> {
+        let value = self.device.on_input()?;
+        self.record(Signal::Input(value));
+        Some(value)
+    }
+
+    fn on_output(&mut self, value: i64) {
+        self.record(Signal::Output(value));
+        self.device.on_output(value);
+    }
+
+    fn at_step(&mut self, steps: usize) {
+        self.step = steps;
+        self.device.at_step(steps);
+    }
+}
+
+#[test]
+fn fixed_and_tape() -> crate::Result<()> {
+    // Outputs double each input until it gets a zero
+    let doubler = crate::intcode::asm::assemble("
+        loop:
+            in  n
+            jf  n, #done
+            mul n, #2, n
+            out n
+            jt  #1, #loop
+        done:
+            hlt
+        n: db 0
+    ")?;
+
+    let mut recorder = Recorder::new(Fixed::new(vec![1, 2, 3, 0]));
+    doubler.clone().run_device(&mut recorder)?;
+    assert_eq!(recorder.device.output, vec![2, 4, 6]);
+    let signals = recorder.tape.events.iter().map(|event| event.signal).take(3).collect::<Vec<Signal>>();
+    assert_eq!(signals, [Signal::Input(1), Signal::Output(2), Signal::Input(2)]);
+    assert_eq!(recorder.tape.to_string().lines().take(2).collect::<Vec<&str>>(), ["0 in 1", "4 out 2"]);
+    assert_eq!(recorder.tape.to_string().parse::<Tape>()?, recorder.tape);
+
+    let mut fixed = Fixed::new(recorder.tape.inputs());
+    doubler.clone().run_device(&mut Recorder::new(&mut fixed))?;
+    assert_eq!(fixed.output, recorder.tape.outputs().collect::<Vec<i64>>());
+
+    let mut program = doubler.clone();
+    program.push_input(5);
+    let mut short = Fixed::new(vec![7]);
+    let error = program.run_device(&mut short);
+    assert_eq!(short.output, vec![10, 14]);
+    assert!(matches!(error, Err(IntcodeError::MissingInput { .. })));
+
+    // Playing the tape back makes the program do just what it did before
+    let mut replay = Recorder::new(recorder.tape.clone());
+    doubler.clone().run_device(&mut replay)?;
+    assert_eq!(replay.tape, recorder.tape);
+
+    Ok(())
+}
+
+#[test]
+fn stdio() -> crate::Result<()> {
+    let program = "3,0,4,0,3,0,4,0,99".parse::<Program>()?;
+    let mut device = Stdio::new(&b"12\n-3\n"[..], Vec::new());
+    program.clone().run_device(&mut device)?;
+    assert_eq!(device.finish()?, b"12\n-3\n");
+
+    let mut device = Stdio::new(&b"12\nx\n"[..], Vec::new());
+    assert!(program.clone().run_device(&mut device).is_err());
+    assert_eq!(device.finish().unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    Ok(())
+}
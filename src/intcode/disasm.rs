@@ -0,0 +1,271 @@
+use crate::intcode::{Instruction, InstructionSet, Mode, OpCode, Program};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Most values to put on one `db` line of a listing
+const DATA_WIDTH: usize = 8;
+
+/// Column the address comments of a listing line up at
+const COMMENT_COLUMN: usize = 40;
+
+/// A decoded instruction parameter
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Operand {
+    pub mode: Mode,
+    pub value: i64,
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.mode.sigil(), self.value)
+    }
+}
+
+/// One line of a listing: either a decoded instruction or a run of data cells
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Line {
+    Code {
+        address: usize,
+        instruction: Instruction,
+        operands: Vec<Operand>,
+    },
+    Data {
+        address: usize,
+        values: Vec<i64>,
+    },
+}
+
+impl Line {
+    pub fn address(&self) -> usize {
+        match self {
+            Line::Code { address, .. } | Line::Data { address, .. } => *address,
+        }
+    }
+
+    /// The raw cells the line was decoded from
+    pub fn words(&self) -> Vec<i64> {
+        match self {
+            Line::Code { instruction, operands, .. } => {
+                std::iter::once(instruction.word)
+                    .chain(operands.iter().map(|operand| operand.value))
+                    .collect()
+            },
+            Line::Data { values, .. } => values.clone(),
+        }
+    }
+
+    /// The assembly text of the line, naming jump targets by their labels
+    fn text(&self, labels: &BTreeMap<usize, String>) -> String {
+        match self {
+            Line::Code { instruction, operands, .. } => {
+                let target = self.jump_target().and_then(|target| labels.get(&target));
+                let operands = operands.iter()
+                    .enumerate()
+                    .map(|(n, operand)| match target {
+                        Some(label) if n == 1 => format!("{}{}", operand.mode.sigil(), label),
+                        _ => operand.to_string(),
+                    })
+                    .collect::<Vec<String>>();
+
+                format!("    {:<4}{}", instruction.opcode.mnemonic(), operands.join(", "))
+            },
+            Line::Data { values, .. } => {
+                let values = values.iter().map(i64::to_string).collect::<Vec<String>>();
+                format!("    {:<4}{}", "db", values.join(", "))
+            },
+        }
+    }
+
+    /// The address this line jumps to, if it is a jump with an immediate target
+    fn jump_target(&self) -> Option<usize> {
+        match self {
+            Line::Code { instruction, operands, .. } => match instruction.opcode {
+                OpCode::JumpIfTrue | OpCode::JumpIfFalse
+                    if operands[1].mode == Mode::Immediate && operands[1].value >= 0 =>
+                {
+                    Some(operands[1].value as usize)
+                },
+                _ => None,
+            },
+            Line::Data { .. } => None,
+        }
+    }
+}
+
+/// A labelled listing of a program image
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Disassembly {
+    pub lines: Vec<Line>,
+    /// Names of the addresses that are jumped to, by address
+    pub labels: BTreeMap<usize, String>,
+}
+
+/// Disassemble the memory of a program.
+///
+/// Decoding runs straight through the image, switching to data after each `hlt` and back to code
+/// at the next jump target. Cells that don't decode to a valid instruction are data too. Since
+/// jump targets are only known once the code jumping to them has been decoded, the sweep repeats
+/// until no new targets turn up.
+pub fn disassemble(program: &Program) -> Disassembly {
+    let image = program.data.iter().copied().collect::<Vec<i64>>();
+    let mut targets = BTreeSet::new();
+
+    loop {
+        let lines = sweep(&image, &targets, &program.instruction_set);
+        let found = lines.iter()
+            .filter_map(Line::jump_target)
+            .filter(|target| *target < image.len() && !targets.contains(target))
+            .collect::<Vec<usize>>();
+
+        if found.is_empty() {
+            let starts = lines.iter().map(Line::address).collect::<BTreeSet<usize>>();
+            let labels = targets.iter()
+                .filter(|target| starts.contains(target))
+                .enumerate()
+                .map(|(i, target)| (*target, format!("L{}", i + 1)))
+                .collect();
+
+            return Disassembly { lines, labels };
+        }
+
+        targets.extend(found);
+    }
+}
+
+fn sweep(image: &[i64], targets: &BTreeSet<usize>, instruction_set: &InstructionSet) -> Vec<Line> {
+    let mut lines: Vec<Line> = Vec::new();
+    let mut address = 0;
+    let mut in_data = false;
+
+    while address < image.len() {
+        if targets.contains(&address) {
+            in_data = false;
+        }
+
+        if !in_data {
+            if let Some(line) = decode(image, address, instruction_set) {
+                if let Line::Code { instruction, .. } = &line {
+                    in_data = instruction.opcode == OpCode::Halt;
+                    address += instruction.opcode.size();
+                }
+                lines.push(line);
+                continue;
+            }
+        }
+
+        match lines.last_mut() {
+            Some(Line::Data { address: start, values })
+                if *start + values.len() == address
+                    && values.len() < DATA_WIDTH
+                    && !targets.contains(&address) =>
+            {
+                values.push(image[address]);
+            },
+            _ => lines.push(Line::Data { address, values: vec![image[address]] }),
+        }
+
+        address += 1;
+    }
+
+    lines
+}
+
+/// Decode the instruction at `address`, built-in or in the instruction set, unless it is invalid
+/// or runs off the end of the image
+pub fn decode(image: &[i64], address: usize, instruction_set: &InstructionSet) -> Option<Line> {
+    let instruction = instruction_set.decode(address, *image.get(address)?).ok()?;
+    let opcode = instruction.opcode;
+
+    if address + opcode.size() > image.len() {
+        return None;
+    }
+
+    if (0..opcode.arity()).any(|n| opcode.writes(n) && instruction.modes[n] == Mode::Immediate) {
+        return None;
+    }
+
+    let operands = (0..opcode.arity())
+        .map(|n| Operand {
+            mode: instruction.modes[n],
+            value: image[address + 1 + n],
+        })
+        .collect();
+
+    Some(Line::Code { address, instruction, operands })
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text(&BTreeMap::new()).trim())
+    }
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in self.lines.iter() {
+            if let Some(label) = self.labels.get(&line.address()) {
+                writeln!(f, "{}:", label)?;
+            }
+
+            let text = format!("{} ", line.text(&self.labels));
+            let words = line.words().iter().map(i64::to_string).collect::<Vec<String>>();
+            writeln!(f, "{:<width$}; {:04}: {}", text, line.address(), words.join(","), width = COMMENT_COLUMN)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn data_after_halt() -> crate::Result<()> {
+    let program = "1,9,10,3,2,3,11,0,99,30,40,50".parse::<Program>()?;
+    let disassembly = disassemble(&program);
+
+    assert_eq!(disassembly.lines.len(), 4);
+    assert_eq!(disassembly.lines[3], Line::Data { address: 9, values: vec![30, 40, 50] });
+    assert!(disassembly.labels.is_empty());
+
+    // Past the end of the image, or only partly in it
+    let image = program.data.iter().copied().collect::<Vec<i64>>();
+    let set = InstructionSet::new();
+    assert_eq!(decode(&image, image.len(), &set), None);
+    assert_eq!(decode(&image, usize::MAX, &set), None);
+    assert_eq!(decode(&image[..6], 4, &set), None);
+
+    Ok(())
+}
+
+#[test]
+fn labelled_listing() -> crate::Result<()> {
+    let program = "3,12,1005,12,9,104,0,99,7,104,1,99,0".parse::<Program>()?;
+    let listing = disassemble(&program).to_string();
+
+    assert_eq!(listing, [
+        "    in  12                              ; 0000: 3,12",
+        "    jt  12, #L1                         ; 0002: 1005,12,9",
+        "    out #0                              ; 0005: 104,0",
+        "    hlt                                 ; 0007: 99",
+        "    db  7                               ; 0008: 7",
+        "L1:",
+        "    out #1                              ; 0009: 104,1",
+        "    hlt                                 ; 0011: 99",
+        "    db  0                               ; 0012: 0",
+        "",
+    ].join("\n"));
+
+    Ok(())
+}
+
+#[test]
+fn whole_inputs() -> crate::Result<()> {
+    for day in [5, 9].iter() {
+        let input = std::fs::read_to_string(format!("input/day{:02}.txt", day))?;
+        let program = input.parse::<Program>()?;
+        let disassembly = disassemble(&program);
+
+        let words = disassembly.lines.iter().flat_map(Line::words).collect::<Vec<i64>>();
+        assert_eq!(words, program.data.iter().copied().collect::<Vec<i64>>());
+    }
+
+    Ok(())
+}
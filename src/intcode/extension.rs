@@ -0,0 +1,232 @@
+use crate::intcode::{IntcodeError, Instruction, OpCode, Program, State};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// How a custom instruction is encoded, and what its parameters are
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct CustomOp {
+    /// The last two digits of the instruction word, which must not be a built-in opcode
+    pub code: i64,
+    pub mnemonic: &'static str,
+    /// At most three parameters, like the built-in instructions
+    pub arity: usize,
+    /// Bit n is set if the instruction writes to parameter n rather than reading it
+    pub write_mask: u8,
+}
+
+impl CustomOp {
+    pub fn new(code: i64, mnemonic: &'static str, arity: usize) -> Self {
+        CustomOp { code, mnemonic, arity, write_mask: 0 }
+    }
+
+    /// The same instruction, writing to parameter `n`
+    pub fn writes_to(mut self, n: usize) -> Self {
+        self.write_mask |= 1 << n;
+        self
+    }
+
+    /// Whether the instruction writes to parameter `n`
+    pub fn writes(self, n: usize) -> bool {
+        self.write_mask & (1 << n) != 0
+    }
+}
+
+/// What a custom instruction did: the state it stops the machine in, if any, and where it goes
+/// next. The default carries on to the next instruction.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Effect {
+    pub state: Option<State>,
+    /// The address to jump to, or None to move on to the next instruction. Jumping past
+    /// `MAX_ADDRESS` faults with `IntcodeError::AddressTooLarge`.
+    pub jump: Option<usize>,
+}
+
+impl Effect {
+    /// Jump to `address` afterwards, even if it's the instruction's own
+    pub fn jump(state: Option<State>, address: usize) -> Self {
+        Effect { state, jump: Some(address) }
+    }
+}
+
+/// Stop in a state, moving on to the next instruction when run again
+impl From<State> for Effect {
+    fn from(state: State) -> Self {
+        Effect { state: Some(state), jump: None }
+    }
+}
+
+/// Carries out a custom instruction. It gets the machine, with `head` still on the instruction,
+/// and one operand per parameter: the value of a read parameter, or the address of a written one.
+/// Where the machine goes next is up to the `Effect` it returns, not `head`, which the handler
+/// shouldn't move. Returning `State::NeedsInput` leaves the instruction to be run again, so a
+/// handler that does that mustn't have changed anything.
+pub type Handler = Arc<dyn Fn(&mut Program, &[i64]) -> Result<Effect, IntcodeError> + Send + Sync>;
+
+/// Why a custom instruction couldn't be registered
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ExtensionError {
+    /// The code isn't two digits, or belongs to a built-in instruction
+    ReservedCode(i64),
+    DuplicateCode(i64),
+    TooManyParameters(usize),
+    /// The write mask names parameters the instruction doesn't have
+    InvalidWriteMask(u8),
+}
+
+impl fmt::Display for ExtensionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExtensionError::ReservedCode(code) => write!(f, "Opcode {} is not available", code),
+            ExtensionError::DuplicateCode(code) => write!(f, "Opcode {} is already registered", code),
+            ExtensionError::TooManyParameters(arity) => write!(f, "{} parameters is more than 3", arity),
+            ExtensionError::InvalidWriteMask(mask) => write!(f, "Write mask {:#b} has parameters the instruction doesn't", mask),
+        }
+    }
+}
+
+impl Error for ExtensionError {}
+
+/// The custom instructions a program understands on top of the built-in ones. Programs share an
+/// instruction set cheaply, and start out with an empty one.
+#[derive(Clone, Default)]
+pub struct InstructionSet {
+    extensions: Arc<BTreeMap<i64, (CustomOp, Handler)>>,
+}
+
+impl InstructionSet {
+    pub fn new() -> Self {
+        InstructionSet::default()
+    }
+
+    /// Add a custom instruction
+    pub fn register<F>(&mut self, op: CustomOp, handler: F) -> Result<(), ExtensionError>
+    where
+        F: Fn(&mut Program, &[i64]) -> Result<Effect, IntcodeError> + Send + Sync + 'static,
+    {
+        if !(1..=98).contains(&op.code) || OpCode::try_from(op.code).is_ok() {
+            return Err(ExtensionError::ReservedCode(op.code));
+        }
+        if self.extensions.contains_key(&op.code) {
+            return Err(ExtensionError::DuplicateCode(op.code));
+        }
+        if op.arity > 3 {
+            return Err(ExtensionError::TooManyParameters(op.arity));
+        }
+        if op.write_mask >> op.arity != 0 {
+            return Err(ExtensionError::InvalidWriteMask(op.write_mask));
+        }
+
+        Arc::make_mut(&mut self.extensions).insert(op.code, (op, Arc::new(handler)));
+        Ok(())
+    }
+
+    /// The custom instructions, by code
+    pub fn custom_ops(&self) -> impl Iterator<Item = CustomOp> + '_ {
+        self.extensions.values().map(|(op, _)| *op)
+    }
+
+    /// Decode an instruction word, built-in or custom
+    pub fn decode(&self, ip: usize, word: i64) -> Result<Instruction, IntcodeError> {
+        Instruction::decode_with(ip, word, |code| {
+            OpCode::try_from(code).ok()
+                .or_else(|| self.extensions.get(&code).map(|(op, _)| OpCode::Custom(*op)))
+        })
+    }
+
+    pub(crate) fn handler(&self, code: i64) -> Option<Handler> {
+        self.extensions.get(&code).map(|(_, handler)| Arc::clone(handler))
+    }
+}
+
+impl fmt::Debug for InstructionSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.custom_ops()).finish()
+    }
+}
+
+/// Instruction sets are equal if they have the same instructions with the same handlers
+impl PartialEq for InstructionSet {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.extensions, &other.extensions)
+            || self.extensions.len() == other.extensions.len()
+                && self.extensions.iter().zip(other.extensions.iter()).all(|((_, (a, f)), (_, (b, g)))| {
+                    a == b && Arc::ptr_eq(f, g)
+                })
+    }
+}
+
+impl Eq for InstructionSet {}
+
+impl Program {
+    /// The program, understanding the custom instructions in `instruction_set` too
+    pub fn with_instruction_set(mut self, instruction_set: InstructionSet) -> Self {
+        self.instruction_set = instruction_set;
+        self
+    }
+}
+
+#[test]
+fn custom_instructions() -> crate::Result<()> {
+    let mut set = InstructionSet::new();
+
+    // 10: square the first parameter into the second
+    set.register(CustomOp::new(10, "sqr", 2).writes_to(1), |program, operands| {
+        let square = operands[0].checked_mul(operands[0])
+            .ok_or(IntcodeError::Overflow { ip: program.head, instruction: program.data[program.head] })?;
+        program.data[operands[1] as usize] = square;
+        Ok(Effect::default())
+    })?;
+
+    // 11: output the first parameter, then jump to the second
+    set.register(CustomOp::new(11, "oj", 2), |_, operands| {
+        Ok(Effect::jump(Some(State::Output(operands[0])), operands[1] as usize))
+    })?;
+
+    // 12: output the first parameter, then run again, forever
+    set.register(CustomOp::new(12, "rep", 1), |program, operands| {
+        Ok(Effect::jump(Some(State::Output(operands[0])), program.head))
+    })?;
+
+    assert_eq!(set.register(CustomOp::new(2, "mul", 3), |_, _| Ok(Effect::default())), Err(ExtensionError::ReservedCode(2)));
+    assert_eq!(set.register(CustomOp::new(10, "sqr", 2), |_, _| Ok(Effect::default())), Err(ExtensionError::DuplicateCode(10)));
+    assert_eq!(set.register(CustomOp::new(13, "x", 1).writes_to(1), |_, _| Ok(Effect::default())),
+        Err(ExtensionError::InvalidWriteMask(2)));
+
+    // Square 7 into 13, output 0 and jump over a halt, then output the square
+    let program = "110,7,13,1111,0,7,99,4,13,99,0,0,0,0".parse::<Program>()?;
+    assert!(matches!(program.clone().run(), Err(IntcodeError::UnknownOpCode { ip: 0, .. })));
+
+    let mut program = program.with_instruction_set(set.clone());
+    assert_eq!(program.run()?, State::Output(0));
+    assert_eq!(program.head, 7);
+    assert_eq!(program.run_to_halt()?, vec![49]);
+
+    // Jumping to its own address runs the instruction again
+    let mut program = "1112,3,99".parse::<Program>()?.with_instruction_set(set.clone());
+    assert_eq!([program.run()?, program.run()?], [State::Output(3); 2]);
+    assert_eq!(program.head, 0);
+
+    // Jumping past the end of memory faults
+    let too_far = crate::intcode::MAX_ADDRESS as i64 + 1;
+    let mut program = format!("1111,5,{}", too_far).parse::<Program>()?.with_instruction_set(set.clone());
+    assert_eq!(program.run(), Err(IntcodeError::AddressTooLarge { ip: 0, instruction: 1111, address: too_far }));
+
+    // The observer sees custom instructions, and what they write
+    let mut program = "110,7,13,99".parse::<Program>()?.with_instruction_set(set);
+    let mut profiler = crate::intcode::trace::Profiler::new();
+    program.run_to_halt_with(&mut profiler)?;
+    assert_eq!(program.data[13], 49);
+    assert_eq!(profiler.total(), 2);
+
+    // Listings and analyses know the custom instructions too
+    let listing = crate::intcode::disasm::disassemble(&program).to_string();
+    assert!(listing.starts_with("    sqr #7, 13 "), "{}", listing);
+    let analysis = crate::intcode::analysis::analyze(&program);
+    assert!(analysis.invalid.is_empty());
+    assert_eq!(analysis.written.keys().copied().collect::<Vec<usize>>(), vec![13]);
+
+    Ok(())
+}
@@ -0,0 +1,262 @@
+//! Random Intcode programs, for checking that every way of running a program agrees on what it
+//! does, and that no program, however malformed, makes any of them panic.
+//!
+//! The tests here run a few hundred programs from fixed seeds. Set `INTCODE_FUZZ_CASES` to run
+//! more. A failure names the seed that produced it, and `Rng::new(seed)` produces it again.
+
+use crate::intcode::{analysis, disasm, solve, Compiled, IntcodeError, OpCode, Program, State, MAX_ADDRESS};
+use std::ops::RangeInclusive;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Most instructions a generated program runs before it is stopped
+const STEP_LIMIT: usize = 2000;
+
+/// Cells in the data area of a generated program
+const DATA_CELLS: i64 = 16;
+
+/// A small seedable random number generator (SplitMix64)
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in the range
+    pub fn range(&mut self, range: RangeInclusive<i64>) -> i64 {
+        let span = range.end().wrapping_sub(*range.start()) as u64;
+        match span.checked_add(1) {
+            Some(span) => range.start().wrapping_add((self.next_u64() % span) as i64),
+            None => self.next_u64() as i64,
+        }
+    }
+
+    /// True `percent` percent of the time
+    pub fn chance(&mut self, percent: u64) -> bool {
+        self.next_u64() % 100 < percent
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[(self.next_u64() % items.len() as u64) as usize]
+    }
+}
+
+/// Assembly source for a random program of `len` instructions, followed by a `hlt` and a data
+/// area. Every opcode and mode turns up. Most jumps go to the start of an instruction, but some
+/// go wherever a value in memory says, and some writes land in the code itself.
+pub fn random_source(rng: &mut Rng, len: usize) -> String {
+    let mut source = String::new();
+
+    for n in 0..len {
+        let mut opcode = *rng.choose(&OpCode::ALL);
+        // Halting this often would make for short runs
+        if opcode == OpCode::Halt && rng.chance(50) {
+            opcode = OpCode::Output;
+        }
+
+        let operands = match opcode {
+            OpCode::Add | OpCode::Mul | OpCode::LessThan | OpCode::Equals => {
+                vec![read_operand(rng), read_operand(rng), write_operand(rng, len)]
+            },
+            OpCode::Input => vec![write_operand(rng, len)],
+            OpCode::Output => vec![read_operand(rng)],
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+                let condition = if rng.chance(50) { format!("#{}", rng.range(0..=1)) } else { read_operand(rng) };
+                let target = if rng.chance(80) { format!("#i{}", rng.range(0..=len as i64 - 1)) } else { read_operand(rng) };
+                vec![condition, target]
+            },
+            OpCode::AdjustBase => vec![if rng.chance(50) { format!("#{}", rng.range(-4..=4)) } else { read_operand(rng) }],
+            OpCode::Halt => vec![],
+            OpCode::Custom(_) => unreachable!("OpCode::ALL has only built-in opcodes"),
+        };
+
+        source += &format!("i{}: {} {}\n", n, opcode.mnemonic(), operands.join(", "));
+    }
+
+    let data = (0..DATA_CELLS).map(|_| rng.range(-20..=20).to_string()).collect::<Vec<String>>();
+    source += &format!("hlt\ndata: db {}\n", data.join(", "));
+
+    source
+}
+
+fn read_operand(rng: &mut Rng) -> String {
+    match rng.range(0..=2) {
+        0 => format!("#{}", rng.range(-20..=20)),
+        1 => format!("data+{}", rng.range(0..=DATA_CELLS - 1)),
+        _ => format!("@{}", rng.range(0..=DATA_CELLS - 1)),
+    }
+}
+
+fn write_operand(rng: &mut Rng, len: usize) -> String {
+    match rng.range(0..=9) {
+        0 => format!("i{}+{}", rng.range(0..=len as i64 - 1), rng.range(0..=3)),
+        1..=5 => format!("data+{}", rng.range(0..=DATA_CELLS - 1)),
+        _ => format!("@{}", rng.range(0..=DATA_CELLS - 1)),
+    }
+}
+
+/// Random words that are mostly almost instructions: bad opcodes and modes, negative, huge and
+/// out of range addresses, and values that overflow
+pub fn random_words(rng: &mut Rng, len: usize) -> Vec<i64> {
+    (0..len)
+        .map(|_| match rng.range(0..=9) {
+            0..=3 => {
+                let opcode = if rng.chance(80) { rng.choose(&OpCode::ALL).code() } else { rng.range(0..=99) };
+                rng.range(0..=999) * 100 + opcode
+            },
+            4..=6 => rng.range(-10..=40),
+            7 => rng.range(1000..=5000),
+            8 => MAX_ADDRESS as i64 + rng.range(-2..=2),
+            _ => rng.range(i64::MIN..=i64::MAX),
+        })
+        .collect()
+}
+
+/// What a run did: what it output, how it stopped, and the machine it stopped with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub output: Vec<i64>,
+    pub stop: Result<State, IntcodeError>,
+    pub program: Program,
+}
+
+/// Run a program with the interpreter, with an observer, and as compiled code, and compare how
+/// each run ends. Each run goes until the program needs input it doesn't have, halts, or fails.
+pub fn differential(program: &Program) -> Result<Outcome, String> {
+    let mut interpreted = program.clone();
+    let (output, stop) = collect(|| interpreted.run());
+    let expected = Outcome { output, stop, program: interpreted };
+
+    let mut observed = program.clone();
+    let mut profiler = crate::intcode::trace::Profiler::new();
+    let (output, stop) = collect(|| observed.run_with(&mut profiler));
+    compare("observed", &expected, Outcome { output, stop, program: observed })?;
+
+    let compiled = Compiled::new(program);
+    let mut runner = compiled.runner(program.clone());
+    let (output, stop) = collect(|| runner.run());
+    compare("compiled", &expected, Outcome { output, stop, program: runner.program })?;
+
+    Ok(expected)
+}
+
+fn collect<F: FnMut() -> Result<State, IntcodeError>>(mut run: F) -> (Vec<i64>, Result<State, IntcodeError>) {
+    let mut output = Vec::new();
+    loop {
+        match run() {
+            Ok(State::Output(value)) => output.push(value),
+            stop => return (output, stop),
+        }
+    }
+}
+
+/// Describe how an outcome differs from the expected one, field by field, since memory can be
+/// too large to print whole
+fn compare(name: &str, expected: &Outcome, outcome: Outcome) -> Result<(), String> {
+    if outcome == *expected {
+        return Ok(());
+    }
+
+    let (a, b) = (&outcome.program, &expected.program);
+    let mut differences = Vec::new();
+    if outcome.output != expected.output {
+        differences.push(format!("output {:?}, not {:?}", outcome.output, expected.output));
+    }
+    if outcome.stop != expected.stop {
+        differences.push(format!("stopped with {:?}, not {:?}", outcome.stop, expected.stop));
+    }
+    if (a.head, a.relative_base, a.steps) != (b.head, b.relative_base, b.steps) {
+        differences.push(format!("head, relative base and steps {:?}, not {:?}",
+            (a.head, a.relative_base, a.steps), (b.head, b.relative_base, b.steps)));
+    }
+    if a.input != b.input {
+        differences.push(format!("input left {:?}, not {:?}", a.input, b.input));
+    }
+    for address in a.data.differences(&b.data).take(10) {
+        differences.push(format!("[{}] = {}, not {}", address, a.data[address], b.data[address]));
+    }
+
+    Err(format!("The {} run differs from the interpreter: {}", name, differences.join("; ")))
+}
+
+/// Check that nothing panics on a program, from disassembling and analysing it to running it
+/// every way there is
+pub fn robust(program: &Program) -> Result<(), String> {
+    let checks: [(&str, &dyn Fn()); 4] = [
+        ("disassembling", &|| { disasm::disassemble(program).to_string(); }),
+        ("analysing", &|| { analysis::analyze(program).dot(); }),
+        ("running symbolically", &|| { solve::symbolic_first(program, &[1, 2]); }),
+        ("running", &|| { let _ = differential(program); }),
+    ];
+
+    for (name, check) in checks.iter() {
+        if catch_unwind(AssertUnwindSafe(check)).is_err() {
+            return Err(format!("Panicked {} {:?}", name, program.data));
+        }
+    }
+
+    Ok(())
+}
+
+fn cases() -> u64 {
+    std::env::var("INTCODE_FUZZ_CASES").ok().and_then(|cases| cases.parse().ok()).unwrap_or(300)
+}
+
+#[test]
+fn generated_programs_agree() {
+    let mut halted = 0;
+
+    for seed in 0..cases() {
+        let mut rng = Rng::new(seed);
+        let len = rng.range(1..=30) as usize;
+        let source = random_source(&mut rng, len);
+        let mut program = crate::intcode::asm::assemble(&source).unwrap_or_else(|e| panic!("Seed {}: {}\n{}", seed, e, source));
+        program.step_limit = Some(STEP_LIMIT);
+        program.extend_input((0..rng.range(0..=4)).map(|_| rng.range(-5..=5)));
+
+        match differential(&program) {
+            Ok(outcome) => halted += (outcome.stop == Ok(State::Halted)) as u64,
+            Err(e) => panic!("Seed {}: {}\n{}", seed, e, source),
+        }
+    }
+
+    // Make sure the generator isn't only making programs that fail straight away
+    assert!(halted > cases() / 10, "Only {} programs halted", halted);
+}
+
+#[test]
+fn malformed_programs_fail_cleanly() {
+    for seed in 0..cases() {
+        let mut rng = Rng::new(seed);
+        let len = rng.range(1..=40) as usize;
+        let mut program = Program::new(random_words(&mut rng, len));
+        program.step_limit = Some(STEP_LIMIT);
+        program.extend_input((0..rng.range(0..=4)).map(|_| rng.range(i64::MIN..=i64::MAX)));
+
+        if let Err(e) = robust(&program).and_then(|_| differential(&program).map(|_| ())) {
+            panic!("Seed {}: {}", seed, e);
+        }
+    }
+}
+
+#[test]
+fn decode_round_trips() {
+    let mut rng = Rng::new(0);
+    for _ in 0..cases() * 100 {
+        let word = if rng.chance(50) { rng.range(-100..=30000) } else { rng.range(i64::MIN..=i64::MAX) };
+        if let Ok(instruction) = crate::intcode::Instruction::decode(0, word) {
+            assert_eq!(crate::intcode::Instruction::new(instruction.opcode, instruction.modes).word, word);
+        }
+    }
+}
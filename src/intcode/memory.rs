@@ -0,0 +1,148 @@
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use std::sync::Arc;
+
+/// Cells per page of memory
+const PAGE_SIZE: usize = 256;
+
+type Page = [i64; PAGE_SIZE];
+
+/// Intcode memory: reads past the end are zero, and writes past the end grow it.
+///
+/// Memory is split into pages that clones share until one of them writes to a page, so cloning
+/// a `Program` to fork it only copies the pages the fork goes on to change.
+#[derive(Default, Clone)]
+pub struct Memory {
+    pages: Vec<Arc<Page>>,
+    len: usize,
+}
+
+impl Memory {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &i64> {
+        self.pages.iter()
+            .flat_map(|page| page.iter())
+            .take(self.len)
+    }
+
+    /// Addresses holding different values in the two memories, in order. Pages the two still
+    /// share are skipped without reading them.
+    pub fn differences<'a>(&'a self, other: &'a Memory) -> impl Iterator<Item = usize> + 'a {
+        let zeroes: &'a Page = &[0; PAGE_SIZE];
+        let pages = self.pages.len().max(other.pages.len());
+
+        (0..pages)
+            .filter_map(move |n| match (self.pages.get(n), other.pages.get(n)) {
+                (Some(a), Some(b)) if Arc::ptr_eq(a, b) => None,
+                (a, b) => Some((n, a.map_or(zeroes, |page| &**page), b.map_or(zeroes, |page| &**page))),
+            })
+            .flat_map(|(n, a, b)| {
+                a.iter()
+                    .zip(b.iter())
+                    .enumerate()
+                    .filter(|(_, (a, b))| a != b)
+                    .map(move |(offset, _)| n * PAGE_SIZE + offset)
+            })
+    }
+
+    /// Number of pages this memory shares with any clones of it
+    pub fn shared_pages(&self) -> usize {
+        self.pages.iter()
+            .filter(|page| Arc::strong_count(page) > 1)
+            .count()
+    }
+}
+
+impl From<Vec<i64>> for Memory {
+    fn from(cells: Vec<i64>) -> Self {
+        let pages = cells.chunks(PAGE_SIZE)
+            .map(|chunk| {
+                let mut page = [0; PAGE_SIZE];
+                page[..chunk.len()].copy_from_slice(chunk);
+                Arc::new(page)
+            })
+            .collect();
+
+        Memory { pages, len: cells.len() }
+    }
+}
+
+impl Index<usize> for Memory {
+    type Output = i64;
+    fn index(&self, address: usize) -> &i64 {
+        match self.pages.get(address / PAGE_SIZE) {
+            Some(page) => &page[address % PAGE_SIZE],
+            None => &0,
+        }
+    }
+}
+
+impl IndexMut<usize> for Memory {
+    fn index_mut(&mut self, address: usize) -> &mut i64 {
+        let page = address / PAGE_SIZE;
+
+        if page >= self.pages.len() {
+            self.pages.resize(page + 1, Arc::new([0; PAGE_SIZE]));
+        }
+        if address >= self.len {
+            self.len = address + 1;
+        }
+
+        &mut Arc::make_mut(&mut self.pages[page])[address % PAGE_SIZE]
+    }
+}
+
+/// Two memories are equal if they hold the same values, counting the zeroes past either end
+impl PartialEq for Memory {
+    fn eq(&self, other: &Self) -> bool {
+        let len = self.len().max(other.len());
+        (0..len).all(|address| self[address] == other[address])
+    }
+}
+
+impl Eq for Memory {}
+
+impl fmt::Debug for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[test]
+fn zero_extends() {
+    let mut memory = Memory::from(vec![1, 2, 3]);
+    assert_eq!(memory[1000], 0);
+    assert_eq!(memory.len(), 3);
+
+    memory[1000] = 7;
+    assert_eq!(memory[1000], 7);
+    assert_eq!(memory.len(), 1001);
+    assert_eq!(memory.iter().filter(|cell| **cell != 0).count(), 4);
+
+    assert_eq!(Memory::from(vec![1, 2, 3]), Memory::from(vec![1, 2, 3, 0, 0]));
+    assert_ne!(Memory::from(vec![1, 2, 3]), Memory::from(vec![1, 2]));
+}
+
+#[test]
+fn copy_on_write() {
+    let original = Memory::from((0..1000).collect::<Vec<i64>>());
+    let mut fork = original.clone();
+    assert_eq!(fork.shared_pages(), 4);
+
+    fork[600] = -1;
+    assert_eq!(fork.shared_pages(), 3);
+    assert_eq!(fork.differences(&original).collect::<Vec<usize>>(), vec![600]);
+
+    fork[1100] = 1;
+    assert_eq!(original.differences(&fork).collect::<Vec<usize>>(), vec![600, 1100]);
+    assert_eq!(original[600], 600);
+    assert_eq!(fork[600], -1);
+    assert_eq!(fork[601], 601);
+}
@@ -0,0 +1,178 @@
+use crate::intcode::{IntcodeError, Program, State};
+use std::collections::VecDeque;
+
+/// The address of the NAT, which holds the last packet sent to it and sends it to address 0
+/// whenever the network goes idle
+pub const NAT: usize = 255;
+
+/// A packet on the network. Packets sent by the NAT come from address `NAT`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Packet {
+    pub from: usize,
+    pub to: i64,
+    pub x: i64,
+    pub y: i64,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Event {
+    /// A packet was sent, to a machine, the NAT, or an address nobody has
+    Sent(Packet),
+    /// A whole round passed with every queue empty and no packets sent
+    Idle,
+}
+
+/// Machines running copies of the same program, passing packets to each other by address
+#[derive(Debug, Clone)]
+pub struct Network {
+    machines: Vec<Program>,
+    /// Packets waiting to be received, by address
+    queues: Vec<VecDeque<(i64, i64)>>,
+    /// Values output by each machine that don't make a whole packet yet
+    partial: Vec<Vec<i64>>,
+    /// The last packet sent to the NAT
+    nat: Option<(i64, i64)>,
+}
+
+impl Network {
+    /// Boot `size` copies of a program, giving each its address as its first input
+    pub fn new(program: &Program, size: usize) -> Self {
+        let machines = (0..size)
+            .map(|address| {
+                let mut machine = program.fork();
+                machine.push_input(address as i64);
+                machine
+            })
+            .collect();
+
+        Network {
+            machines,
+            queues: vec![VecDeque::new(); size],
+            partial: vec![Vec::new(); size],
+            nat: None,
+        }
+    }
+
+    /// Run rounds until the hook returns false for an event, or every machine halts
+    pub fn run<F: FnMut(&Event) -> bool>(&mut self, mut hook: F) -> Result<(), IntcodeError> {
+        loop {
+            if !self.round(&mut hook)? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Give every machine a turn, in address order. Each one receives its next packet, or -1 if
+    /// it has none, and runs until it needs more input. Returns false once the hook returns
+    /// false, or if every machine has halted.
+    pub fn round<F: FnMut(&Event) -> bool>(&mut self, hook: &mut F) -> Result<bool, IntcodeError> {
+        let mut sent = false;
+        let mut running = false;
+
+        for address in 0..self.machines.len() {
+            let machine = &mut self.machines[address];
+
+            if machine.input.is_empty() {
+                match self.queues[address].pop_front() {
+                    Some((x, y)) => machine.extend_input(vec![x, y]),
+                    None => machine.push_input(-1),
+                }
+            }
+
+            loop {
+                match self.machines[address].run()? {
+                    State::Output(value) => {
+                        self.partial[address].push(value);
+                        if self.partial[address].len() == 3 {
+                            let packet = Packet {
+                                from: address,
+                                to: self.partial[address][0],
+                                x: self.partial[address][1],
+                                y: self.partial[address][2],
+                            };
+                            self.partial[address].clear();
+                            sent = true;
+
+                            if !self.send(packet, hook) {
+                                return Ok(false);
+                            }
+                        }
+                    },
+                    State::NeedsInput => {
+                        running = true;
+                        break;
+                    },
+                    State::Halted => break,
+                }
+            }
+        }
+
+        if !sent && self.queues.iter().all(VecDeque::is_empty) {
+            if !hook(&Event::Idle) {
+                return Ok(false);
+            }
+
+            if let Some((x, y)) = self.nat {
+                if !self.send(Packet { from: NAT, to: 0, x, y }, hook) {
+                    return Ok(false);
+                }
+            }
+        }
+
+        Ok(running)
+    }
+
+    /// Deliver a packet and report it to the hook. Packets to nobody are dropped.
+    fn send<F: FnMut(&Event) -> bool>(&mut self, packet: Packet, hook: &mut F) -> bool {
+        if packet.to == NAT as i64 {
+            self.nat = Some((packet.x, packet.y));
+        } else if packet.to >= 0 && (packet.to as usize) < self.queues.len() {
+            self.queues[packet.to as usize].push_back((packet.x, packet.y));
+        }
+
+        hook(&Event::Sent(packet))
+    }
+}
+
+#[test]
+fn nat_wakes_idle_network() -> crate::Result<()> {
+    let program = crate::intcode::asm::assemble("
+            in  addr
+            jt  addr, #loop         ; only machine 0 starts the traffic
+            out #1
+            out #7
+            out #100
+        loop:
+            in  x
+            eq  x, #-1, flag
+            jt  flag, #loop
+            in  y
+            add y, addr, y
+            out #255
+            out x
+            out y
+            jt  #1, #loop
+        addr: db 0
+        x: db 0
+        y: db 0
+        flag: db 0
+    ")?;
+
+    let mut network = Network::new(&program, 2);
+    let mut events = Vec::new();
+    network.run(|event| {
+        events.push(*event);
+        events.len() < 6
+    })?;
+
+    assert_eq!(events, vec![
+        Event::Sent(Packet { from: 0, to: 1, x: 7, y: 100 }),
+        Event::Sent(Packet { from: 1, to: 255, x: 7, y: 101 }),
+        Event::Idle,
+        Event::Sent(Packet { from: NAT, to: 0, x: 7, y: 101 }),
+        Event::Sent(Packet { from: 0, to: 255, x: 7, y: 101 }),
+        Event::Idle,
+    ]);
+
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use crate::intcode::{IntcodeError, Program, State};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+/// Machines that each run on their own thread, with their outputs wired to each other's inputs
+/// over channels. Any topology works: a chain, a ring that feeds back on itself, or one machine
+/// fanning out to several.
+///
+/// A machine that needs input blocks until a value arrives. If every machine that could send it
+/// one has halted, it fails with `IntcodeError::MissingInput`. Values sent to a machine that has
+/// already halted are dropped. Machines that wait on each other in a cycle with nothing in flight
+/// wait forever.
+#[derive(Debug)]
+pub struct Pipeline {
+    machines: Vec<Program>,
+    inputs: Vec<(Sender<i64>, Receiver<i64>)>,
+    /// Where each machine's output goes
+    outputs: Vec<Vec<Sender<i64>>>,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Pipeline::new()
+    }
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Pipeline {
+            machines: Vec::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Add a machine, returning its index for wiring. Any input already queued on the program is
+    /// read before anything that arrives over the wire.
+    pub fn add(&mut self, program: Program) -> usize {
+        self.machines.push(program);
+        self.inputs.push(channel());
+        self.outputs.push(Vec::new());
+        self.machines.len() - 1
+    }
+
+    /// Send everything machine `from` outputs to machine `to`
+    pub fn connect(&mut self, from: usize, to: usize) {
+        let sender = self.inputs[to].0.clone();
+        self.outputs[from].push(sender);
+    }
+
+    /// Queue input for a machine before it starts
+    pub fn push_input(&mut self, machine: usize, value: i64) {
+        self.machines[machine].push_input(value);
+    }
+
+    /// Receive a copy of everything a machine outputs
+    pub fn tap(&mut self, machine: usize) -> Receiver<i64> {
+        let (sender, receiver) = channel();
+        self.outputs[machine].push(sender);
+        receiver
+    }
+
+    /// Run every machine until they have all halted, returning the last value each of them
+    /// output, by machine index. If any machine fails, returns the error from the lowest
+    /// numbered one.
+    pub fn run(self) -> Result<Vec<Option<i64>>, IntcodeError> {
+        let threads = self.machines.into_iter()
+            .zip(self.inputs)
+            .zip(self.outputs)
+            .map(|((program, (_, input)), outputs)| thread::spawn(move || drive(program, input, outputs)))
+            .collect::<Vec<_>>();
+
+        // Wait for every machine before looking at how any of them finished
+        let finished = threads.into_iter()
+            .map(|thread| thread.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+            .collect::<Vec<_>>();
+        finished.into_iter().collect()
+    }
+}
+
+/// Run a machine until it halts, returning the last value it output
+fn drive(mut program: Program, input: Receiver<i64>, outputs: Vec<Sender<i64>>) -> Result<Option<i64>, IntcodeError> {
+    let mut last = None;
+    loop {
+        match program.run()? {
+            State::Output(value) => {
+                last = Some(value);
+                for output in outputs.iter() {
+                    // The receiving machine may have halted already
+                    let _ = output.send(value);
+                }
+            },
+            State::NeedsInput => match input.recv() {
+                Ok(value) => program.push_input(value),
+                Err(_) => return Err(program.missing_input()),
+            },
+            State::Halted => return Ok(last),
+        }
+    }
+}
+
+#[test]
+fn fan_out() -> crate::Result<()> {
+    // Doubles each input until it reads 0
+    let double = crate::intcode::asm::assemble("
+        loop:
+            in  x
+            jf  x, #done
+            mul x, #2, x
+            out x
+            jt  #1, #loop
+        done:
+            hlt
+        x: db 0
+    ")?;
+
+    let mut pipeline = Pipeline::new();
+    let source = pipeline.add("104,1,104,2,104,0,99".parse()?);
+    let left = pipeline.add(double.clone());
+    let right = pipeline.add(double);
+    pipeline.connect(source, left);
+    pipeline.connect(source, right);
+    let taps = [pipeline.tap(left), pipeline.tap(right)];
+
+    assert_eq!(pipeline.run()?, vec![Some(0), Some(4), Some(4)]);
+    for tap in taps.iter() {
+        assert_eq!(tap.iter().collect::<Vec<i64>>(), vec![2, 4]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn starved() -> crate::Result<()> {
+    let mut pipeline = Pipeline::new();
+    let source = pipeline.add("104,1,99".parse()?);
+    let sink = pipeline.add("3,0,3,0,99".parse()?);
+    pipeline.connect(source, sink);
+
+    assert_eq!(pipeline.run(), Err(IntcodeError::MissingInput { ip: 2, instruction: 3 }));
+
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use crate::intcode::device::{Device, Event, Signal, Tape};
+use crate::intcode::{IntcodeError, Program};
+use std::error::Error;
+use std::fmt;
+
+/// What a replayed program did instead of what was recorded
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Found {
+    Input,
+    Output(i64),
+    Halt,
+}
+
+/// The first point where a replayed program stops doing what it did when it was recorded
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Divergence {
+    /// Which event on the tape
+    pub index: usize,
+    /// The recorded event, or `None` if the tape had ended
+    pub expected: Option<Event>,
+    /// The number of instructions the program had executed
+    pub step: usize,
+    pub found: Found,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Diverged at event {}: expected ", self.index)?;
+        match self.expected {
+            Some(event) => write!(f, "{}", event)?,
+            None => write!(f, "the end of the tape")?,
+        }
+        match self.found {
+            Found::Input => write!(f, ", but the program asked for input at step {}", self.step),
+            Found::Output(value) => write!(f, ", but the program output {} at step {}", value, self.step),
+            Found::Halt => write!(f, ", but the program halted at step {}", self.step),
+        }
+    }
+}
+
+impl Error for Divergence {}
+
+/// Plays a tape back to a program, feeding it the recorded input, and checks that it asks for
+/// input and outputs at the same steps and with the same values as it did when recorded. Once it
+/// diverges it gives no more input, which stops the run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    tape: Tape,
+    /// The next event expected
+    position: usize,
+    step: usize,
+    divergence: Option<Divergence>,
+}
+
+impl Replay {
+    pub fn new(tape: Tape) -> Self {
+        Replay { tape, position: 0, step: 0, divergence: None }
+    }
+
+    /// The first divergence, if there's been one
+    pub fn divergence(&self) -> Option<&Divergence> {
+        self.divergence.as_ref()
+    }
+
+    /// Check the next event is the one expected, recording a divergence if not
+    fn expect(&mut self, found: Found) -> Option<Signal> {
+        if self.divergence.is_some() {
+            return None;
+        }
+
+        let expected = self.tape.events.get(self.position).copied();
+        let matches = match (expected, found) {
+            (Some(Event { step, signal: Signal::Input(_) }), Found::Input) => step == self.step,
+            (Some(Event { step, signal: Signal::Output(a) }), Found::Output(b)) => step == self.step && a == b,
+            (None, Found::Halt) => true,
+            _ => false,
+        };
+
+        if !matches {
+            self.divergence = Some(Divergence { index: self.position, expected, step: self.step, found });
+            return None;
+        }
+
+        self.position += 1;
+        expected.map(|event| event.signal)
+    }
+}
+
+impl Device for Replay {
+    fn on_input(&mut self) -> Option<i64> {
+        match self.expect(Found::Input)? {
+            Signal::Input(value) => Some(value),
+            Signal::Output(_) => unreachable!("Replay matched input to an output"),
+        }
+    }
+
+    fn on_output(&mut self, value: i64) {
+        self.expect(Found::Output(value));
+    }
+
+    fn at_step(&mut self, steps: usize) {
+        self.step = steps;
+    }
+}
+
+impl Program {
+    /// Run the program against a tape, stopping at the first divergence from it. A program that
+    /// doesn't diverge halts right where the tape ends.
+    pub fn replay(&mut self, tape: &Tape) -> Result<Option<Divergence>, IntcodeError> {
+        let mut replay = Replay::new(tape.clone());
+        let result = self.run_device(&mut replay);
+        if replay.divergence.is_none() {
+            result?;
+            replay.at_step(self.steps);
+            replay.expect(Found::Halt);
+        }
+
+        Ok(replay.divergence)
+    }
+}
+
+#[test]
+fn divergence() -> crate::Result<()> {
+    use crate::intcode::device::{Fixed, Queued, Recorder};
+
+    let adder = crate::intcode::asm::assemble("
+            in  a
+            in  b
+            add a, b, a
+            out a
+            hlt
+        a: db 0
+        b: db 0
+    ")?;
+
+    let mut recorder = Recorder::new(Fixed::new(vec![2, 3]));
+    adder.clone().run_device(&mut recorder)?;
+    let tape = recorder.tape;
+    assert_eq!(tape.to_string(), "0 in 2\n1 in 3\n4 out 5\n");
+    assert_eq!(adder.clone().replay(&tape)?, None);
+
+    // The same inputs, but a different answer
+    let mut multiplier = adder.clone();
+    multiplier.data[4] = 2;
+    let divergence = multiplier.replay(&tape)?.unwrap();
+    assert_eq!(divergence.index, 2);
+    assert_eq!(divergence.found, Found::Output(6));
+    assert_eq!(divergence.to_string(), "Diverged at event 2: expected 4 out 5, but the program output 6 at step 4");
+
+    // Input asked for at another step
+    let mut late = tape.clone();
+    late.events[1].step = 2;
+    let divergence = adder.clone().replay(&late)?.unwrap();
+    assert_eq!((divergence.index, divergence.step, divergence.found), (1, 1, Found::Input));
+
+    // Tapes that end too soon and go on too long
+    let mut short = tape.clone();
+    short.events.pop();
+    assert_eq!(adder.clone().replay(&short)?.unwrap().expected, None);
+    let mut long = tape.clone();
+    long.events.push(tape.events[2]);
+    assert_eq!(adder.clone().replay(&long)?.unwrap().found, Found::Halt);
+
+    // Input queued ahead of the run is recorded when the program reads it, so the tape replays
+    // without it
+    let mut recorder = Recorder::new(Queued::new(vec![2], Fixed::new(vec![3])));
+    adder.clone().run_device(&mut recorder)?;
+    assert_eq!(recorder.tape, tape);
+
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use crate::intcode::{Memory, Program};
+use crate::InputError;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The complete state of a machine at some point in its run: memory, instruction pointer,
+/// relative base, step count and pending input.
+///
+/// Snapshots share memory pages with the machine they were taken from, so taking one is cheap.
+/// They can be written to disk as text:
+///
+/// ```text
+/// head 4
+/// relative_base 0
+/// steps 1
+/// step_limit none
+/// input 5,6
+/// memory 1101,2,3,5,99,5
+/// ```
+///
+/// The text doesn't include the machine's instruction set, since handlers are code. A snapshot
+/// loaded from text has an empty one, so give it back with `Program::with_instruction_set`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    program: Program,
+}
+
+impl Program {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot { program: self.clone() }
+    }
+
+    /// Put the machine back into the state of a snapshot
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        *self = snapshot.program.clone();
+    }
+
+    /// An independent copy of the machine. It shares memory with this one until either of them
+    /// writes to it, so forking a large program thousands of times stays cheap.
+    pub fn fork(&self) -> Program {
+        self.clone()
+    }
+}
+
+impl Snapshot {
+    /// A machine that resumes from this snapshot
+    pub fn program(&self) -> Program {
+        self.program.clone()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> crate::Result<()> {
+        Ok(fs::write(path, self.to_string())?)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        Ok(fs::read_to_string(path)?.parse::<Snapshot>()?)
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let program = &self.program;
+        let join = |values: &mut dyn Iterator<Item = &i64>| {
+            values.map(i64::to_string).collect::<Vec<String>>().join(",")
+        };
+
+        writeln!(f, "head {}", program.head)?;
+        writeln!(f, "relative_base {}", program.relative_base)?;
+        writeln!(f, "steps {}", program.steps)?;
+        match program.step_limit {
+            Some(limit) => writeln!(f, "step_limit {}", limit)?,
+            None => writeln!(f, "step_limit none")?,
+        }
+        writeln!(f, "input {}", join(&mut program.input.iter()))?;
+        writeln!(f, "memory {}", join(&mut program.data.iter()))
+    }
+}
+
+impl FromStr for Snapshot {
+    type Err = InputError<String>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut program = Program::new(Vec::new());
+
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let invalid = || InputError::new(line.to_string());
+            let mut split = line.trim().splitn(2, ' ');
+            let key = split.next().unwrap_or("");
+            let value = split.next().unwrap_or("").trim();
+
+            match key {
+                "head" => program.head = value.parse().map_err(|_| invalid())?,
+                "relative_base" => program.relative_base = value.parse().map_err(|_| invalid())?,
+                "steps" => program.steps = value.parse().map_err(|_| invalid())?,
+                "step_limit" => program.step_limit = match value {
+                    "none" => None,
+                    limit => Some(limit.parse().map_err(|_| invalid())?),
+                },
+                "input" => program.input = parse_cells(value)
+                    .map_err(|_| invalid())?
+                    .into_iter()
+                    .collect::<VecDeque<i64>>(),
+                "memory" => program.data = Memory::from(parse_cells(value).map_err(|_| invalid())?),
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(Snapshot { program })
+    }
+}
+
+fn parse_cells(s: &str) -> Result<Vec<i64>, std::num::ParseIntError> {
+    if s.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    s.split(',').map(|cell| cell.trim().parse::<i64>()).collect()
+}
+
+#[test]
+fn snapshot_and_restore() -> crate::Result<()> {
+    use crate::intcode::State;
+
+    // Outputs the sum of each pair of inputs
+    let mut program = "3,11,3,12,1,11,12,13,4,13,99,0,0,0".parse::<Program>()?;
+    program.push_input(2);
+    assert_eq!(program.run()?, State::NeedsInput);
+
+    let snapshot = program.snapshot();
+    program.push_input(3);
+    assert_eq!(program.run()?, State::Output(5));
+
+    program.restore(&snapshot);
+    program.push_input(40);
+    assert_eq!(program.run()?, State::Output(42));
+
+    Ok(())
+}
+
+#[test]
+fn text_round_trip() -> crate::Result<()> {
+    let mut program = "1101,2,3,5,99,0".parse::<Program>()?;
+    program.step()?;
+    program.extend_input(vec![5, -6]);
+    program.step_limit = Some(100);
+
+    let text = program.snapshot().to_string();
+    assert_eq!(text, "head 4\nrelative_base 0\nsteps 1\nstep_limit 100\ninput 5,-6\nmemory 1101,2,3,5,99,5\n");
+    assert_eq!(text.parse::<Snapshot>()?.program(), program);
+
+    assert!("head four".parse::<Snapshot>().is_err());
+    assert!("registers 1".parse::<Snapshot>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn fork_shares_memory() -> crate::Result<()> {
+    let input = fs::read_to_string("input/day09.txt")?;
+    let program = input.parse::<Program>()?;
+
+    let mut forks = (1..=2).map(|mode| {
+        let mut fork = program.fork();
+        fork.push_input(mode);
+        fork
+    }).collect::<Vec<Program>>();
+    assert_eq!(forks[0].data.shared_pages(), program.data.shared_pages());
+
+    assert_eq!(forks[0].run_to_halt()?, vec![3601950151]);
+    assert_eq!(forks[1].run_to_halt()?, vec![64236]);
+    assert_eq!(program.input.len(), 0);
+    assert_eq!(program, input.parse::<Program>()?);
+
+    Ok(())
+}
@@ -0,0 +1,428 @@
+use crate::intcode::{Instruction, Mode, OpCode, Program, MAX_ADDRESS};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// The variables of a term, by address, with their powers
+type Monomial = BTreeMap<usize, u32>;
+
+/// Instructions each run of `find_inputs` may execute, unless the program has its own step limit
+pub const SEARCH_STEPS: usize = 1_000_000;
+
+/// What solving a polynomial found out
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Solution {
+    Found(Vec<i64>),
+    /// No values in the ranges give the target
+    NoneExists,
+    /// Some values couldn't be ruled out, because the polynomial overflowed on them or there were
+    /// too many to try
+    Unknown,
+}
+
+/// A polynomial with integer coefficients, in variables that stand for the values of memory cells
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Polynomial {
+    terms: BTreeMap<Monomial, i64>,
+}
+
+impl Polynomial {
+    pub fn constant(value: i64) -> Self {
+        let mut terms = BTreeMap::new();
+        if value != 0 {
+            terms.insert(Monomial::new(), value);
+        }
+        Polynomial { terms }
+    }
+
+    /// The value of the cell at `address`
+    pub fn variable(address: usize) -> Self {
+        let mut monomial = Monomial::new();
+        monomial.insert(address, 1);
+        let mut terms = BTreeMap::new();
+        terms.insert(monomial, 1);
+        Polynomial { terms }
+    }
+
+    /// The value, if it doesn't depend on any variable
+    pub fn as_constant(&self) -> Option<i64> {
+        match self.terms.len() {
+            0 => Some(0),
+            1 => self.terms.get(&Monomial::new()).copied(),
+            _ => None,
+        }
+    }
+
+    /// None if a coefficient overflows
+    pub fn checked_add(&self, other: &Polynomial) -> Option<Polynomial> {
+        let mut terms = self.terms.clone();
+        for (monomial, coefficient) in other.terms.iter() {
+            let sum = terms.get(monomial).copied().unwrap_or(0).checked_add(*coefficient)?;
+            if sum == 0 {
+                terms.remove(monomial);
+            } else {
+                terms.insert(monomial.clone(), sum);
+            }
+        }
+        Some(Polynomial { terms })
+    }
+
+    /// None if a coefficient or a power overflows
+    pub fn checked_mul(&self, other: &Polynomial) -> Option<Polynomial> {
+        let mut product = Polynomial::default();
+        for (a, a_coefficient) in self.terms.iter() {
+            for (b, b_coefficient) in other.terms.iter() {
+                let mut monomial = a.clone();
+                for (address, power) in b.iter() {
+                    let sum = monomial.get(address).copied().unwrap_or(0).checked_add(*power)?;
+                    monomial.insert(*address, sum);
+                }
+                let mut term = Polynomial::default();
+                term.terms.insert(monomial, a_coefficient.checked_mul(*b_coefficient)?);
+                product = product.checked_add(&term)?;
+            }
+        }
+        Some(product)
+    }
+
+    /// The value with each variable set, or None if it overflows. Unset variables are zero.
+    pub fn evaluate(&self, values: &BTreeMap<usize, i64>) -> Option<i64> {
+        self.terms.iter().try_fold(0i64, |sum, (monomial, coefficient)| {
+            let term = monomial.iter().try_fold(*coefficient, |term, (address, power)| {
+                term.checked_mul(values.get(address).copied().unwrap_or(0).checked_pow(*power)?)
+            })?;
+            sum.checked_add(term)
+        })
+    }
+
+    /// Split into `a` and `b` where this is `a * x + b` and neither depends on `x`, the variable
+    /// at `address`. None if this isn't linear in `x`.
+    fn linear_in(&self, address: usize) -> Option<(Polynomial, Polynomial)> {
+        let (mut a, mut b) = (Polynomial::default(), Polynomial::default());
+        for (monomial, coefficient) in self.terms.iter() {
+            match monomial.get(&address) {
+                None => { b.terms.insert(monomial.clone(), *coefficient); },
+                Some(1) => {
+                    let mut monomial = monomial.clone();
+                    monomial.remove(&address);
+                    a.terms.insert(monomial, *coefficient);
+                },
+                Some(_) => return None,
+            }
+        }
+        Some((a, b))
+    }
+}
+
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+
+        // Highest degree first, and the constant last
+        for (n, (monomial, coefficient)) in self.terms.iter().rev().enumerate() {
+            let variables = monomial.iter()
+                .map(|(address, power)| match power {
+                    1 => format!("[{}]", address),
+                    _ => format!("[{}]^{}", address, power),
+                })
+                .collect::<Vec<String>>();
+
+            let sign = match (n, *coefficient < 0) {
+                (0, true) => "-",
+                (0, false) => "",
+                (_, true) => " - ",
+                (_, false) => " + ",
+            };
+            let magnitude = coefficient.unsigned_abs();
+
+            match (magnitude, variables.is_empty()) {
+                (_, true) => write!(f, "{}{}", sign, magnitude)?,
+                (1, false) => write!(f, "{}{}", sign, variables.join("*"))?,
+                (_, false) => write!(f, "{}{}*{}", sign, magnitude, variables.join("*"))?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Run a program symbolically, with the cells at `variables` unknown, and return the value it
+/// leaves at address 0 as a polynomial in them.
+///
+/// Only straight line programs of `add` and `mul` in position and immediate mode, ending in `hlt`,
+/// can run this way. A read from an address that depends on a variable gives a value that can't be
+/// known, which is fine as long as it is overwritten before anything uses it. Anything else,
+/// including an instruction or write address that depends on a variable, or a coefficient that
+/// overflows, gives None.
+pub fn symbolic_first(program: &Program, variables: &[usize]) -> Option<Polynomial> {
+    // None for a value that can't be known. Cells not in the map are zero, so writes far past
+    // the end of the program don't need the memory in between.
+    let mut memory = program.data.iter()
+        .enumerate()
+        .map(|(address, value)| (address, Some(Polynomial::constant(*value))))
+        .collect::<HashMap<usize, Option<Polynomial>>>();
+    for address in variables {
+        if *address > MAX_ADDRESS {
+            return None;
+        }
+        memory.insert(*address, Some(Polynomial::variable(*address)));
+    }
+
+    let cell = |memory: &HashMap<usize, Option<Polynomial>>, address: usize| {
+        memory.get(&address).cloned().unwrap_or_else(|| Some(Polynomial::default()))
+    };
+    let mut ip = program.head;
+
+    loop {
+        let word = cell(&memory, ip)?.as_constant()?;
+        let instruction = Instruction::decode(ip, word).ok()?;
+
+        match instruction.opcode {
+            OpCode::Halt => return cell(&memory, 0),
+            OpCode::Add | OpCode::Mul => (),
+            _ => return None,
+        }
+
+        // The address of each parameter, if it can be known
+        let mut params = Vec::new();
+        for n in 0..3 {
+            params.push(match instruction.modes[n] {
+                Mode::Position => match cell(&memory, ip + 1 + n).and_then(|operand| operand.as_constant()) {
+                    Some(address) if address < 0 => return None,
+                    address => address.map(|address| address as usize),
+                },
+                Mode::Immediate if n < 2 => Some(ip + 1 + n),
+                _ => return None,
+            });
+        }
+
+        let target = params[2].filter(|target| *target <= MAX_ADDRESS)?;
+        let read = |param: Option<usize>| param.and_then(|address| cell(&memory, address));
+        let value = match (read(params[0]), read(params[1])) {
+            (Some(a), Some(b)) if instruction.opcode == OpCode::Add => a.checked_add(&b),
+            (Some(a), Some(b)) => a.checked_mul(&b),
+            _ => None,
+        };
+
+        memory.insert(target, value);
+        ip += instruction.opcode.size();
+    }
+}
+
+impl Program {
+    /// Find values for the cells at `cells`, each within its range, that make the program halt
+    /// with `target` at address 0. Of all the answers, returns the first in order of the cells.
+    ///
+    /// Programs that `symbolic_first` can handle are solved from their polynomial, and the answer
+    /// is checked by running it. A polynomial that proves there is no answer is taken at its word.
+    /// Any other program, or one whose answer doesn't check out, is run for every combination of
+    /// values, in parallel. Each run stops after the program's step limit,
+    /// or `SEARCH_STEPS` if it has none. There is no answer if there are more combinations than
+    /// fit in a usize.
+    pub fn find_inputs(&self, cells: &[usize], ranges: &[RangeInclusive<i64>], target: i64) -> Option<Vec<i64>> {
+        assert_eq!(cells.len(), ranges.len(), "One range is needed for each cell");
+
+        let solved = symbolic_first(self, cells).map(|polynomial| solve(&polynomial, cells, ranges, target));
+        match solved {
+            Some(Solution::Found(values)) if self.gives(cells, &values, target) => Some(values),
+            Some(Solution::NoneExists) => None,
+            _ => self.search_inputs(cells, ranges, target),
+        }
+    }
+
+    /// Whether the program halts with `target` at address 0 when the cells hold the values
+    fn gives(&self, cells: &[usize], values: &[i64], target: i64) -> bool {
+        let mut program = self.fork();
+        program.step_limit = program.step_limit.or(Some(SEARCH_STEPS));
+        for (cell, value) in cells.iter().zip(values) {
+            if *cell > MAX_ADDRESS {
+                return false;
+            }
+            program.data[*cell] = *value;
+        }
+        program.run_to_halt().is_ok() && program.first() == target
+    }
+
+    /// Run the program for every combination of values, in parallel, keeping the first that gives
+    /// the target
+    fn search_inputs(&self, cells: &[usize], ranges: &[RangeInclusive<i64>], target: i64) -> Option<Vec<i64>> {
+        let combinations = combinations(ranges)?;
+        let threads = thread::available_parallelism().map(usize::from).unwrap_or(1);
+        let best = AtomicUsize::new(usize::MAX);
+
+        thread::scope(|scope| {
+            for first in 0..threads {
+                let best = &best;
+                scope.spawn(move || {
+                    for index in (first..combinations).step_by(threads) {
+                        if index >= best.load(Ordering::Relaxed) {
+                            return;
+                        }
+
+                        if self.gives(cells, &combination(ranges, index), target) {
+                            best.fetch_min(index, Ordering::Relaxed);
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        match best.into_inner() {
+            usize::MAX => None,
+            index => Some(combination(ranges, index)),
+        }
+    }
+}
+
+/// The number of values in a range, or None if it doesn't fit in a usize
+fn width(range: &RangeInclusive<i64>) -> Option<usize> {
+    let width = i128::from(*range.end()) - i128::from(*range.start()) + 1;
+    usize::try_from(width.max(0)).ok()
+}
+
+/// The number of combinations of values in the ranges, or None if it doesn't fit in a usize
+fn combinations(ranges: &[RangeInclusive<i64>]) -> Option<usize> {
+    ranges.iter().try_fold(1usize, |product, range| product.checked_mul(width(range)?))
+}
+
+/// The values at an index into every combination of the ranges, counting through the last range
+/// fastest. The index must be less than `combinations` of the ranges.
+fn combination(ranges: &[RangeInclusive<i64>], mut index: usize) -> Vec<i64> {
+    let mut values = vec![0; ranges.len()];
+    for (value, range) in values.iter_mut().zip(ranges.iter()).rev() {
+        let len = width(range).unwrap_or(usize::MAX);
+        // The offset is within the range, so adding it can only wrap where an i64 does
+        *value = range.start().wrapping_add((index % len) as i64);
+        index /= len;
+    }
+    values
+}
+
+/// Solve `polynomial == target` over the ranges. If it's linear in the last cell, that cell is
+/// solved for directly for each combination of the others.
+fn solve(polynomial: &Polynomial, cells: &[usize], ranges: &[RangeInclusive<i64>], target: i64) -> Solution {
+    let (last, others) = match cells.split_last() {
+        Some(split) => split,
+        None if polynomial.as_constant() == Some(target) => return Solution::Found(Vec::new()),
+        None => return Solution::NoneExists,
+    };
+    let last_range = &ranges[ranges.len() - 1];
+    let linear = polynomial.linear_in(*last);
+
+    let combinations = match combinations(&ranges[..others.len()]) {
+        Some(combinations) => combinations,
+        None => return Solution::Unknown,
+    };
+    let mut unknown = false;
+
+    for index in 0..combinations {
+        let mut values = others.iter()
+            .copied()
+            .zip(combination(&ranges[..others.len()], index))
+            .collect::<BTreeMap<usize, i64>>();
+
+        let solved = match &linear {
+            Some((a, b)) => match (a.evaluate(&values), b.evaluate(&values)) {
+                (Some(0), Some(b)) if b == target => Some(*last_range.start()),
+                (Some(0), Some(_)) => None,
+                (Some(a), Some(b)) => match target.checked_sub(b) {
+                    Some(difference) if difference % a == 0 => Some(difference / a)
+                        .filter(|value| last_range.contains(value)),
+                    Some(_) => None,
+                    None => { unknown = true; None },
+                },
+                _ => { unknown = true; None },
+            },
+            None => last_range.clone().find(|value| {
+                values.insert(*last, *value);
+                let value = polynomial.evaluate(&values);
+                unknown |= value.is_none();
+                value == Some(target)
+            }),
+        };
+
+        if let Some(value) = solved {
+            values.insert(*last, value);
+            return Solution::Found(cells.iter().map(|cell| values[cell]).collect());
+        }
+    }
+
+    if unknown { Solution::Unknown } else { Solution::NoneExists }
+}
+
+#[test]
+fn day02_is_linear() -> crate::Result<()> {
+    let program = std::fs::read_to_string("input/day02.txt")?.parse::<Program>()?;
+    let polynomial = symbolic_first(&program, &[1, 2]).expect("Day 2 is add and mul only");
+
+    assert!(polynomial.linear_in(1).is_some() && polynomial.linear_in(2).is_some());
+    let values = [(1, 12), (2, 2)].iter().copied().collect();
+    let mut run = program.change_values(12, 2);
+    run.run_to_halt()?;
+    assert_eq!(polynomial.evaluate(&values), Some(run.first()));
+
+    Ok(())
+}
+
+#[test]
+fn find_inputs() -> crate::Result<()> {
+    let program = std::fs::read_to_string("input/day02.txt")?.parse::<Program>()?;
+    assert_eq!(program.find_inputs(&[1, 2], &[0..=99, 0..=99], 19690720), Some(vec![52, 96]));
+
+    // Not linear in either cell
+    let product = "2,5,6,0,99,0,0".parse::<Program>()?;
+    assert_eq!(symbolic_first(&product, &[5, 6]).map(|p| p.to_string()), Some("[5]*[6]".to_string()));
+    assert_eq!(product.find_inputs(&[5, 6], &[0..=20, 0..=20], 42), Some(vec![3, 14]));
+
+    // Jumps can't run symbolically, so this is searched
+    let jumps = crate::intcode::asm::assemble("
+            jt  #1, #start
+        start:
+            mul a, b, 0
+            hlt
+        a: db 0
+        b: db 0
+    ")?;
+    assert_eq!(symbolic_first(&jumps, &[8, 9]), None);
+    assert_eq!(jumps.find_inputs(&[8, 9], &[0..=20, 0..=20], 42), Some(vec![3, 14]));
+    assert_eq!(jumps.find_inputs(&[8, 9], &[0..=20, 0..=20], 401), None);
+
+    // Ranges too wide to count, or empty
+    assert_eq!(product.find_inputs(&[5, 6], &[i64::MIN..=i64::MAX, 0..=20], 42), None);
+    assert_eq!(product.find_inputs(&[5, 6], &[0..=20, RangeInclusive::new(20, 0)], 42), None);
+
+    // A variable far past the end of the program
+    let far = format!("1,5,{},0,99,2", MAX_ADDRESS);
+    let polynomial = symbolic_first(&far.parse()?, &[MAX_ADDRESS]);
+    assert_eq!(polynomial.map(|p| p.to_string()), Some(format!("[{}] + 2", MAX_ADDRESS)));
+
+    // Proven to have no answer, so the trillions of combinations aren't searched
+    assert_eq!(solve(&symbolic_first(&product, &[5, 6]).unwrap(), &[5, 6], &[0..=1000, 0..=1 << 40], -1),
+        Solution::NoneExists);
+    assert_eq!(product.find_inputs(&[5, 6], &[0..=1000, 0..=1 << 40], -1), None);
+
+    // Cells past the end of memory can't be set
+    assert_eq!(jumps.find_inputs(&[MAX_ADDRESS + 1], &[0..=3], 0), None);
+
+    // Powers that don't fit
+    let x = Polynomial::variable(5);
+    let mut power = x.clone();
+    for _ in 0..31 {
+        power = power.checked_mul(&power).unwrap();
+    }
+    assert_eq!(power.to_string(), format!("[5]^{}", 1u32 << 31));
+    assert_eq!(power.checked_mul(&power), None);
+
+    // A program that never halts gives up on each run
+    let mut forever = "1105,1,0".parse::<Program>()?;
+    forever.step_limit = Some(100);
+    assert_eq!(forever.find_inputs(&[5], &[0..=3], 0), None);
+
+    Ok(())
+}
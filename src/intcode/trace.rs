@@ -0,0 +1,244 @@
+use crate::intcode::{Instruction, OpCode};
+use crate::json_string;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+
+/// Width of the longest bar in a profile histogram
+const BAR_WIDTH: usize = 40;
+
+/// One executed instruction, as reported to an `Observer`
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Executed {
+    pub ip: usize,
+    pub instruction: Instruction,
+    /// The resolved value of each parameter that is read, and the resolved address of the one
+    /// that is written. A jump that isn't taken has its target as written in the program, since
+    /// it never resolves it. Cells past the instruction's arity are zero.
+    pub operands: [i64; 3],
+    /// The address written to, and the value written there
+    pub written: Option<(usize, i64)>,
+}
+
+/// Something that watches a `Program` execute, one instruction at a time
+pub trait Observer {
+    fn on_step(&mut self, executed: &Executed);
+}
+
+/// Observe nothing. Running with this costs the same as running unobserved.
+impl Observer for () {
+    #[inline(always)]
+    fn on_step(&mut self, _executed: &Executed) {}
+}
+
+impl<O: Observer> Observer for &mut O {
+    fn on_step(&mut self, executed: &Executed) {
+        (**self).on_step(executed);
+    }
+}
+
+/// Observe with two observers at once
+impl<A: Observer, B: Observer> Observer for (A, B) {
+    fn on_step(&mut self, executed: &Executed) {
+        self.0.on_step(executed);
+        self.1.on_step(executed);
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Format {
+    /// `0004 lt 1187 34463338 63 -> [63] = 1`
+    Lines,
+    /// One JSON object per line
+    JsonLines,
+}
+
+/// Writes one record per executed instruction
+#[derive(Debug)]
+pub struct Tracer<W: Write> {
+    writer: W,
+    format: Format,
+    /// The first write error, which stops the trace
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(writer: W, format: Format) -> Self {
+        Tracer { writer, format, error: None }
+    }
+
+    /// Flush the trace and give the writer back, or the first error writing to it
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn record(&mut self, executed: &Executed) -> io::Result<()> {
+        let opcode = executed.instruction.opcode;
+        let operands = &executed.operands[..opcode.arity()];
+
+        match self.format {
+            Format::Lines => {
+                write!(self.writer, "{:04} {}", executed.ip, opcode.mnemonic())?;
+                for operand in operands {
+                    write!(self.writer, " {}", operand)?;
+                }
+                if let Some((address, value)) = executed.written {
+                    write!(self.writer, " -> [{}] = {}", address, value)?;
+                }
+                writeln!(self.writer)
+            },
+            Format::JsonLines => {
+                let operands = operands.iter().map(i64::to_string).collect::<Vec<String>>();
+                write!(self.writer,
+                    "{{\"ip\":{},\"instruction\":{},\"opcode\":{},\"operands\":[{}]",
+                    executed.ip, executed.instruction.word, json_string(opcode.mnemonic()), operands.join(","))?;
+                match executed.written {
+                    Some((address, value)) => {
+                        write!(self.writer, ",\"written\":{{\"address\":{},\"value\":{}}}", address, value)?
+                    },
+                    None => write!(self.writer, ",\"written\":null")?,
+                }
+                writeln!(self.writer, "}}")
+            },
+        }
+    }
+}
+
+impl<W: Write> Observer for Tracer<W> {
+    fn on_step(&mut self, executed: &Executed) {
+        if self.error.is_none() {
+            if let Err(e) = self.record(executed) {
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+/// Counts executed instructions by opcode and by address
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Profiler {
+    pub by_opcode: HashMap<OpCode, u64>,
+    pub by_address: HashMap<usize, u64>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler::default()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.by_opcode.values().sum()
+    }
+
+    /// The most executed addresses, most executed first
+    pub fn hottest(&self, n: usize) -> Vec<(usize, u64)> {
+        let mut addresses = self.by_address.iter()
+            .map(|(address, count)| (*address, *count))
+            .collect::<Vec<_>>();
+        addresses.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        addresses.truncate(n);
+        addresses
+    }
+}
+
+impl Observer for Profiler {
+    fn on_step(&mut self, executed: &Executed) {
+        *self.by_opcode.entry(executed.instruction.opcode).or_insert(0) += 1;
+        *self.by_address.entry(executed.ip).or_insert(0) += 1;
+    }
+}
+
+impl fmt::Display for Profiler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let max = self.by_opcode.values().max().copied().unwrap_or(0).max(1);
+
+        writeln!(f, "{} instructions", self.total())?;
+        for opcode in OpCode::ALL.iter() {
+            if let Some(count) = self.by_opcode.get(opcode) {
+                let bar = "#".repeat((*count as usize * BAR_WIDTH + max as usize - 1) / max as usize);
+                writeln!(f, "{:<4}{:>12} {}", opcode.mnemonic(), count, bar)?;
+            }
+        }
+
+        writeln!(f, "Hottest addresses:")?;
+        for (address, count) in self.hottest(10) {
+            writeln!(f, "{:04}{:>12}", address, count)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn counter() -> crate::intcode::Program {
+    crate::intcode::asm::assemble("
+        loop:
+            add count, #1, count
+            lt  count, #3, flag
+            jt  flag, #loop
+            out count
+            hlt
+        count: db 0
+        flag: db 0
+    ").unwrap()
+}
+
+#[test]
+fn trace_lines() -> crate::Result<()> {
+    let mut tracer = Tracer::new(Vec::new(), Format::Lines);
+    counter().run_to_halt_with(&mut tracer)?;
+    let trace = String::from_utf8(tracer.finish()?)?;
+
+    assert_eq!(trace.lines().take(4).collect::<Vec<&str>>(), vec![
+        "0000 add 0 1 14 -> [14] = 1",
+        "0004 lt 1 3 15 -> [15] = 1",
+        "0008 jt 1 0",
+        "0000 add 1 1 14 -> [14] = 2",
+    ]);
+    assert_eq!(trace.lines().last(), Some("0013 hlt"));
+
+    Ok(())
+}
+
+#[test]
+fn trace_json() -> crate::Result<()> {
+    let mut tracer = Tracer::new(Vec::new(), Format::JsonLines);
+    counter().run_to_halt_with(&mut tracer)?;
+    let trace = String::from_utf8(tracer.finish()?)?;
+
+    assert_eq!(
+        trace.lines().next(),
+        Some("{\"ip\":0,\"instruction\":1001,\"opcode\":\"add\",\"operands\":[0,1,14],\"written\":{\"address\":14,\"value\":1}}"),
+    );
+    assert_eq!(
+        trace.lines().nth(2),
+        Some("{\"ip\":8,\"instruction\":1005,\"opcode\":\"jt\",\"operands\":[1,0],\"written\":null}"),
+    );
+
+    // Custom mnemonics can have anything in them
+    let mut set = crate::intcode::InstructionSet::new();
+    set.register(crate::intcode::CustomOp::new(10, "say \"hi\"", 0), |_, _| Ok(crate::intcode::Effect::default()))?;
+    let mut tracer = Tracer::new(Vec::new(), Format::JsonLines);
+    "10,99".parse::<crate::intcode::Program>()?.with_instruction_set(set).run_to_halt_with(&mut tracer)?;
+    let trace = String::from_utf8(tracer.finish()?)?;
+    assert!(trace.starts_with("{\"ip\":0,\"instruction\":10,\"opcode\":\"say \\\"hi\\\"\",\"operands\":[],"), "{}", trace);
+
+    Ok(())
+}
+
+#[test]
+fn profile() -> crate::Result<()> {
+    let mut profiler = Profiler::new();
+    counter().run_to_halt_with(&mut profiler)?;
+
+    assert_eq!(profiler.total(), 11);
+    assert_eq!(profiler.by_opcode[&OpCode::Add], 3);
+    assert_eq!(profiler.by_opcode[&OpCode::Halt], 1);
+    assert_eq!(profiler.hottest(2), vec![(0, 3), (4, 3)]);
+
+    Ok(())
+}
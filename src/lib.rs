@@ -1,19 +1,31 @@
 pub mod intcode;
 pub use intcode::*;
 
+pub mod solver;
+pub use solver::{Answer, Solver};
+
+pub mod answers;
+pub mod input;
+
+pub mod day01;
+pub mod day02;
+pub mod day03;
+pub mod day04;
+pub mod day05;
+pub mod day07;
+pub mod day09;
+pub mod day11;
+pub mod day13;
+pub mod day17;
+pub mod day21;
+pub mod day23;
+
 use std::error::Error;
 use std::fmt;
 
 pub type BoxError = Box<dyn Error>;
 pub type Result<T> = std::result::Result<T, BoxError>;
 
-#[macro_export]
-macro_rules! answer {
-    ($day: expr, $part: expr, $answer: expr) => {
-        println!("Day {}, Part {}: {}", $day, stringify!($part), $answer);
-    }
-}
-
 #[macro_export]
 macro_rules! boxerr {
     ($err: expr) => {
@@ -46,3 +58,21 @@ impl<T: fmt::Debug> fmt::Display for InputError<T> {
 }
 
 impl<T: fmt::Debug> Error for InputError<T> {}
+
+/// A string as a quoted JSON string
+pub fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
@@ -1,69 +1,116 @@
 use advent_of_code_2019::*;
+use std::io;
+use std::process::ExitCode;
 
 mod cli;
+mod report;
 
-mod day01;
-mod day02;
-mod day03;
-mod day04;
-mod day05;
-mod day06;
-mod day07;
-mod day08;
-mod day09;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
-mod day15;
-mod day16;
-mod day17;
-mod day18;
-mod day19;
-mod day20;
-mod day21;
-mod day22;
-mod day23;
-mod day24;
-mod day25;
+fn main() -> ExitCode {
+    match run() {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
 
-fn main() -> Result<()> {
+fn run() -> Result<ExitCode> {
     let matches = cli::app().get_matches();
-    let input = matches.value_of("input");
 
-    if let Some(days) = matches.values_of("day") {
-        for day in days {
-            match day.parse::<u8>().unwrap() {
-                1  => day01::main(input)?,
-                2  => day02::main(input)?,
-                3  => day03::main(input)?,
-                4  => day04::main(input)?,
-                5  => day05::main(input)?,
-                6  => day06::main(input)?,
-                7  => day07::main(input)?,
-                8  => day08::main(input)?,
-                9  => day09::main(input)?,
-                10 => day10::main(input)?,
-                11 => day11::main(input)?,
-                12 => day12::main(input)?,
-                13 => day13::main(input)?,
-                14 => day14::main(input)?,
-                15 => day15::main(input)?,
-                16 => day16::main(input)?,
-                17 => day17::main(input)?,
-                18 => day18::main(input)?,
-                19 => day19::main(input)?,
-                20 => day20::main(input)?,
-                21 => day21::main(input)?,
-                22 => day22::main(input)?,
-                23 => day23::main(input)?,
-                24 => day24::main(input)?,
-                25 => day25::main(input)?,
-                d => unreachable!("Not a day: {}", d),
+    match matches.subcommand() {
+        ("disasm", Some(matches)) => {
+            print!("{}", disasm::disassemble(&cli::program(matches)?));
+            return Ok(ExitCode::SUCCESS);
+        },
+        ("analyze", Some(matches)) => {
+            let analysis = analysis::analyze(&cli::program(matches)?);
+            if matches.is_present("dot") {
+                print!("{}", analysis.dot());
+            } else {
+                print!("{}", analysis);
+            }
+            return Ok(ExitCode::SUCCESS);
+        },
+        ("debug", Some(matches)) => {
+            let mut debugger = debug::Debugger::new(cli::program(matches)?);
+            debugger.repl(io::stdin().lock(), io::stdout())?;
+            return Ok(ExitCode::SUCCESS);
+        },
+        ("trace", Some(matches)) => {
+            let format = if matches.is_present("json") { trace::Format::JsonLines } else { trace::Format::Lines };
+            let mut tracer = trace::Tracer::new(io::stdout().lock(), format);
+            let result = cli::program(matches)?.run_to_halt_within_with(&mut tracer, &cli::budget(matches)?);
+            let _stdout = tracer.finish()?;
+            result??;
+            return Ok(ExitCode::SUCCESS);
+        },
+        ("record", Some(matches)) => {
+            let mut program = cli::program(matches)?;
+            // Fed input goes on the tape like any other, so it replays without being fed again
+            let fed = std::mem::take(&mut program.input);
+            let mut recorder = device::Recorder::new(device::Queued::new(fed, device::Stdio::terminal()));
+            let result = program.run_device(&mut recorder);
+            std::fs::write(matches.value_of("tape").unwrap(), recorder.tape.to_string())?;
+            recorder.device.device.finish()?;
+            result?;
+            return Ok(ExitCode::SUCCESS);
+        },
+        ("replay", Some(matches)) => {
+            let tape = std::fs::read_to_string(matches.value_of("tape").unwrap())?.parse::<device::Tape>()?;
+            match cli::program(matches)?.replay(&tape)? {
+                Some(divergence) => return Err(divergence.into()),
+                None => println!("Replayed {} events", tape.events.len()),
+            }
+            return Ok(ExitCode::SUCCESS);
+        },
+        ("profile", Some(matches)) => {
+            let mut profiler = trace::Profiler::new();
+            let result = cli::program(matches)?.run_to_halt_within_with(&mut profiler, &cli::budget(matches)?);
+            print!("{}", profiler);
+            result??;
+            return Ok(ExitCode::SUCCESS);
+        },
+        _ => (),
+    }
+
+    let days = cli::days(&matches)?;
+    let inputs = cli::inputs(&matches, &days)?;
+
+    let format = cli::format(&matches);
+    let timeout = cli::timeout(&matches)?;
+    let answers = match matches.is_present("check") {
+        true => Some(answers::Answers::load(matches.value_of("answers").unwrap_or(answers::DEFAULT_PATH))?),
+        false => None,
+    };
+
+    if let Some(header) = format.header() {
+        println!("{}", header);
+    }
+    let reports = days.iter()
+        .map(|day| {
+            let mut report = report::run(*day, &inputs, timeout);
+            if let Some(answers) = &answers {
+                report.expect(answers);
             }
-        }
+            format.print(&report);
+            report
+        })
+        .collect::<Vec<_>>();
+    if let Some(footer) = format.footer(&reports) {
+        print!("{}", footer);
+    }
+
+    let failed = reports.iter().filter(|report| report.failed()).count();
+    if failed > 0 {
+        eprintln!("{} of {} days failed", failed, reports.len());
+        return Ok(ExitCode::FAILURE);
+    }
+    let wrong = reports.iter().flat_map(report::Report::checks).flatten().filter(|check| *check == answers::Check::Fail).count();
+    if wrong > 0 {
+        eprintln!("{} answers didn't match the known ones", wrong);
+        return Ok(ExitCode::FAILURE);
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
@@ -0,0 +1,374 @@
+use advent_of_code_2019::answers::{Answers, Check};
+use advent_of_code_2019::input::Inputs;
+use advent_of_code_2019::solver::{self, Puzzle};
+use advent_of_code_2019::{json_string, Answer};
+use std::fmt;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How running one day went
+#[derive(Debug)]
+pub struct Report {
+    pub day: u8,
+    pub outcome: Outcome,
+    /// Each part's known answer, when checking against them
+    pub expected: Option<[Option<Answer>; 2]>,
+}
+
+#[derive(Debug)]
+pub enum Outcome {
+    NotImplemented,
+    /// An input couldn't be read or parsed
+    Failed(String),
+    Ran { parse: Duration, parts: [Part; 2] },
+}
+
+/// One part's answer, or why there isn't one, and the wall time spent on it
+#[derive(Debug)]
+pub struct Part {
+    pub answer: Result<Answer, String>,
+    pub time: Duration,
+}
+
+impl Report {
+    /// Whether anything went wrong, short of the day not being implemented
+    pub fn failed(&self) -> bool {
+        match &self.outcome {
+            Outcome::NotImplemented => false,
+            Outcome::Failed(_) => true,
+            Outcome::Ran { parts, .. } => parts.iter().any(|part| part.answer.is_err()),
+        }
+    }
+
+    /// Check the answers against the known ones
+    pub fn expect(&mut self, answers: &Answers) {
+        self.expected = Some([1, 2].map(|part| answers.get(self.day, part).cloned()));
+    }
+
+    /// How each part compares with its known answer, when checking
+    pub fn checks(&self) -> Option<[Check; 2]> {
+        let expected = self.expected.as_ref()?;
+        Some([0, 1].map(|n| Check::new(expected[n].as_ref(), self.answer(n))))
+    }
+
+    /// A part's answer, if it has one
+    pub fn answer(&self, n: usize) -> Option<&Answer> {
+        match &self.outcome {
+            Outcome::Ran { parts, .. } => parts[n].answer.as_ref().ok(),
+            _ => None,
+        }
+    }
+
+    /// One record for each part
+    pub fn records(&self) -> Vec<Record<'_>> {
+        (0..2)
+            .map(|n| {
+                let (answer, time, error) = match &self.outcome {
+                    Outcome::NotImplemented => (None, None, Some("not implemented")),
+                    Outcome::Failed(e) => (None, None, Some(e.as_str())),
+                    Outcome::Ran { parts, .. } => match &parts[n] {
+                        Part { answer: Ok(answer), time } => (Some(answer), Some(*time), None),
+                        Part { answer: Err(e), time } => (None, Some(*time), Some(e.as_str())),
+                    },
+                };
+                let check = self.checks().map(|checks| checks[n]);
+                Record { day: self.day, part: n + 1, answer, time, error, check }
+            })
+            .collect()
+    }
+}
+
+/// Run a day's solver on its inputs, giving up on it if it takes longer than `timeout`
+pub fn run(day: u8, inputs: &Inputs, timeout: Option<Duration>) -> Report {
+    let outcome = match solver::solver(day) {
+        Some(solver) => solve(day, solver, inputs, timeout).unwrap_or_else(|e| Outcome::Failed(e.to_string())),
+        None => Outcome::NotImplemented,
+    };
+
+    Report { day, outcome, expected: None }
+}
+
+fn solve(day: u8, solver: &'static dyn Puzzle, inputs: &Inputs, timeout: Option<Duration>) -> advent_of_code_2019::Result<Outcome> {
+    // The parts only get an input each if they're given different ones
+    let sources = [inputs.source(day, 1), inputs.source(day, 2)];
+    let first = inputs.read(day, &sources[0])?;
+    let second = match sources[1] != sources[0] {
+        true => Some(inputs.read(day, &sources[1])?),
+        false => None,
+    };
+
+    // A solver can't be interrupted, so one that runs out of time is left running on its own
+    // thread until the process exits
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let outcome = solve_parts(solver, &first, second.as_deref()).unwrap_or_else(|e| Outcome::Failed(e.to_string()));
+        sender.send(outcome).ok();
+    });
+
+    let outcome = match timeout {
+        Some(timeout) => receiver.recv_timeout(timeout),
+        None => receiver.recv().map_err(RecvTimeoutError::from),
+    };
+    Ok(match outcome {
+        Ok(outcome) => outcome,
+        Err(RecvTimeoutError::Timeout) => Outcome::Failed(format!("timed out after {:?}", timeout.unwrap_or_default())),
+        Err(RecvTimeoutError::Disconnected) => Outcome::Failed("the solver panicked".to_string()),
+    })
+}
+
+fn solve_parts(solver: &dyn Puzzle, first: &str, second: Option<&str>) -> advent_of_code_2019::Result<Outcome> {
+    let start = Instant::now();
+    let first = solver.parse(first)?;
+    let second = second.map(|second| solver.parse(second)).transpose()?;
+    let parse = start.elapsed();
+
+    let part = |solve: &dyn Fn() -> advent_of_code_2019::Result<Answer>| {
+        let start = Instant::now();
+        let answer = solve().map_err(|e| e.to_string());
+        Part { answer, time: start.elapsed() }
+    };
+    let parts = [part(&|| solver.part1(&first)), part(&|| solver.part2(second.as_ref().unwrap_or(&first)))];
+
+    Ok(Outcome::Ran { parse, parts })
+}
+
+/// How the answers are written out
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Format {
+    /// A line per answer, then a summary table if there's more than one day
+    Text,
+    /// One JSON object per part
+    Json,
+    /// A header, then one row per part
+    Csv,
+}
+
+impl Format {
+    pub const NAMES: [&'static str; 3] = ["text", "json", "csv"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+
+    /// Anything to write before the first report
+    pub fn header(self) -> Option<&'static str> {
+        match self {
+            Format::Csv => Some("day,part,answer,seconds,error,check"),
+            _ => None,
+        }
+    }
+
+    /// Print a day's report as soon as it's done
+    pub fn print(self, report: &Report) {
+        match (self, &report.outcome) {
+            (Format::Text, _) if report.expected.is_some() => print_checks(report),
+            (Format::Text, Outcome::NotImplemented) => println!("Day {}: not implemented", report.day),
+            (Format::Text, Outcome::Failed(e)) => println!("Day {}: failed: {}", report.day, e),
+            (Format::Text, Outcome::Ran { parts, .. }) => {
+                for (n, part) in parts.iter().enumerate() {
+                    match &part.answer {
+                        Ok(answer) => println!("Day {}, Part {}: {}", report.day, n + 1, text(answer)),
+                        Err(e) => println!("Day {}, Part {}: failed: {}", report.day, n + 1, e),
+                    }
+                }
+            },
+            (Format::Json, _) => report.records().iter().for_each(|record| println!("{}", record.json())),
+            (Format::Csv, _) => report.records().iter().for_each(|record| println!("{}", record.csv())),
+        }
+    }
+
+    /// Anything to write after the last report
+    pub fn footer(self, reports: &[Report]) -> Option<String> {
+        match self {
+            Format::Text => {
+                let mut footer = String::new();
+                if reports.len() > 1 {
+                    footer = format!("\n{}", Summary(reports));
+                }
+                let checks = reports.iter().flat_map(Report::checks).flatten().collect::<Vec<_>>();
+                if !checks.is_empty() {
+                    let count = |check| checks.iter().filter(|c| **c == check).count();
+                    footer += &format!("\nChecked: {} passed, {} failed, {} missing\n",
+                        count(Check::Pass), count(Check::Fail), count(Check::Missing));
+                }
+                Some(footer).filter(|footer| !footer.is_empty())
+            },
+            _ => None,
+        }
+    }
+}
+
+/// A line per part saying whether it got its known answer, and what it got instead if not
+fn print_checks(report: &Report) {
+    let expected = report.expected.as_ref().expect("Checking without known answers");
+    for (record, expected) in report.records().iter().zip(expected) {
+        match (record.check, expected, record.answer) {
+            (Some(Check::Fail), Some(expected), Some(found)) =>
+                println!("Day {}, Part {}: fail: expected {}, found {}", record.day, record.part, text(expected), text(found)),
+            (Some(Check::Fail), Some(expected), None) =>
+                println!("Day {}, Part {}: fail: expected {} ({})", record.day, record.part, text(expected), record.error.unwrap_or("no answer")),
+            (check, _, _) => println!("Day {}, Part {}: {}", record.day, record.part, check.unwrap_or(Check::Missing)),
+        }
+    }
+}
+
+/// An answer as text. One that takes more than one line, like a picture, starts on a line of its
+/// own.
+fn text(answer: &Answer) -> String {
+    match answer.to_string() {
+        text if text.contains('\n') => format!("\n{}", text),
+        text => text,
+    }
+}
+
+/// The machine-readable result of one part of one day
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record<'a> {
+    pub day: u8,
+    pub part: usize,
+    pub answer: Option<&'a Answer>,
+    /// Unknown if the part never ran
+    pub time: Option<Duration>,
+    pub error: Option<&'a str>,
+    /// How the answer compares with the known one, when checking
+    pub check: Option<Check>,
+}
+
+impl Record<'_> {
+    pub fn json(&self) -> String {
+        let answer = match self.answer {
+            Some(Answer::Number(n)) => n.to_string(),
+            Some(Answer::Text(s)) => json_string(s),
+            None => "null".to_string(),
+        };
+        let seconds = self.time.map_or("null".to_string(), |time| time.as_secs_f64().to_string());
+        let error = self.error.map_or("null".to_string(), json_string);
+        let check = self.check.map_or("null".to_string(), |check| json_string(&check.to_string()));
+
+        format!("{{\"day\":{},\"part\":{},\"answer\":{},\"seconds\":{},\"error\":{},\"check\":{}}}",
+            self.day, self.part, answer, seconds, error, check)
+    }
+
+    pub fn csv(&self) -> String {
+        let answer = self.answer.map(|answer| csv_field(&answer.to_string())).unwrap_or_default();
+        let seconds = self.time.map(|time| time.as_secs_f64().to_string()).unwrap_or_default();
+        let error = self.error.map(csv_field).unwrap_or_default();
+        let check = self.check.map(|check| check.to_string()).unwrap_or_default();
+
+        format!("{},{},{},{},{},{}", self.day, self.part, answer, seconds, error, check)
+    }
+}
+
+/// Quote a field if it has anything in it that would end it early
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// An aligned table of every day's answers and times, with the total time
+pub struct Summary<'a>(pub &'a [Report]);
+
+impl fmt::Display for Summary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut rows = vec![["Day", "Part 1", "Part 2", "Parse", "Time 1", "Time 2", "Total"].map(String::from).to_vec()];
+        let mut total = Duration::default();
+
+        for report in self.0 {
+            let mut row = vec![report.day.to_string()];
+            match &report.outcome {
+                Outcome::NotImplemented => row.push("not implemented".to_string()),
+                Outcome::Failed(e) => row.push(format!("failed: {}", e)),
+                Outcome::Ran { parse, parts } => {
+                    row.extend(parts.iter().map(|part| match &part.answer {
+                        Ok(answer) => cell(answer),
+                        Err(_) => "failed".to_string(),
+                    }));
+                    let day_total = *parse + parts[0].time + parts[1].time;
+                    row.extend([*parse, parts[0].time, parts[1].time, day_total].iter().map(duration));
+                    total += day_total;
+                },
+            }
+            rows.push(row);
+        }
+
+        // Columns are as wide as their widest cell, leaving out the notes that take up a whole row
+        let mut widths = [0; 7];
+        for row in rows.iter().filter(|row| row.len() == 7) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        for row in &rows {
+            let line = match row.len() {
+                2 => format!("{:>width$}  {}", row[0], row[1], width = widths[0]),
+                _ => row.iter()
+                    .zip(&widths)
+                    .enumerate()
+                    .map(|(n, (cell, width))| match n {
+                        1 | 2 => format!("{:<width$}", cell, width = width),
+                        _ => format!("{:>width$}", cell, width = width),
+                    })
+                    .collect::<Vec<String>>()
+                    .join("  "),
+            };
+            writeln!(f, "{}", line.trim_end())?;
+        }
+
+        let width = widths.iter().sum::<usize>() + 2 * (widths.len() - 1);
+        writeln!(f, "{:>width$}", format!("Total {}", duration(&total)), width = width)
+    }
+}
+
+/// An answer as it fits in a table cell. Pictures spread over lines are printed in full above.
+fn cell(answer: &Answer) -> String {
+    match answer {
+        Answer::Text(text) if text.trim().contains('\n') => "(see above)".to_string(),
+        answer => answer.to_string(),
+    }
+}
+
+fn duration(duration: &Duration) -> String {
+    format!("{:.1?}", duration)
+}
+
+#[test]
+fn records() {
+    let report = Report {
+        day: 11,
+        outcome: Outcome::Ran {
+            parse: Duration::from_millis(1),
+            parts: [
+                Part { answer: Ok(Answer::Number(2428)), time: Duration::from_millis(250) },
+                Part { answer: Err("Said \"no\", twice".to_string()), time: Duration::from_millis(5) },
+            ],
+        },
+        expected: None,
+    };
+    let records = report.records();
+    assert!(report.failed());
+    assert_eq!(records[0].json(), r#"{"day":11,"part":1,"answer":2428,"seconds":0.25,"error":null,"check":null}"#);
+    assert_eq!(records[1].json(), r#"{"day":11,"part":2,"answer":null,"seconds":0.005,"error":"Said \"no\", twice","check":null}"#);
+    assert_eq!(records[1].csv(), r#"11,2,,0.005,"Said ""no"", twice","#);
+
+    let mut report = Report { day: 6, outcome: Outcome::NotImplemented, expected: None };
+    assert!(!report.failed());
+    assert_eq!(report.records()[1].csv(), "6,2,,,not implemented,");
+    assert_eq!(Record { answer: Some(&Answer::from("A\nB")), ..report.records()[0].clone() }.json(),
+        r#"{"day":6,"part":1,"answer":"A\nB","seconds":null,"error":"not implemented","check":null}"#);
+
+    let mut answers = Answers::default();
+    answers.insert(6, 1, Answer::Number(1));
+    report.expect(&answers);
+    assert_eq!(report.checks(), Some([Check::Fail, Check::Missing]));
+    assert_eq!(report.records()[0].csv(), "6,1,,,not implemented,fail");
+}
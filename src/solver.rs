@@ -0,0 +1,124 @@
+use crate::*;
+use std::any::Any;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// The answer to one part of a puzzle
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Answer {
+    Number(i64),
+    /// An answer that isn't a number, like letters read off a picture
+    Text(String),
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Answer::Number(n) => write!(f, "{}", n),
+            Answer::Text(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// A number too big for an i64 is kept as text, rather than wrapping
+macro_rules! answer_from_int {
+    ($($t: ty),*) => {
+        $(impl From<$t> for Answer {
+            fn from(n: $t) -> Self {
+                i64::try_from(n).map_or_else(|_| Answer::Text(n.to_string()), Answer::Number)
+            }
+        })*
+    }
+}
+
+answer_from_int!(i16, i32, i64, isize, u16, u32, usize);
+
+impl From<String> for Answer {
+    fn from(s: String) -> Self {
+        Answer::Text(s)
+    }
+}
+
+impl From<&str> for Answer {
+    fn from(s: &str) -> Self {
+        Answer::Text(s.to_string())
+    }
+}
+
+/// Solves the puzzle for one day: parses the puzzle input once, then answers each part from it
+pub trait Solver {
+    type Input: 'static;
+
+    fn parse(&self, input: &str) -> Result<Self::Input>;
+    fn part1(&self, input: &Self::Input) -> Result<Answer>;
+    fn part2(&self, input: &Self::Input) -> Result<Answer>;
+}
+
+/// Puzzle input parsed by a `Puzzle`, to hand back to it
+pub struct Parsed(Box<dyn Any>);
+
+/// A `Solver` with its input type hidden, so the solvers for different days can be kept together
+pub trait Puzzle: Sync {
+    fn parse(&self, input: &str) -> Result<Parsed>;
+    fn part1(&self, input: &Parsed) -> Result<Answer>;
+    fn part2(&self, input: &Parsed) -> Result<Answer>;
+
+    /// Parse the input and answer both parts
+    fn solve(&self, input: &str) -> Result<[Answer; 2]> {
+        let input = self.parse(input)?;
+        Ok([self.part1(&input)?, self.part2(&input)?])
+    }
+}
+
+impl<S: Solver + Sync> Puzzle for S {
+    fn parse(&self, input: &str) -> Result<Parsed> {
+        Ok(Parsed(Box::new(Solver::parse(self, input)?)))
+    }
+
+    fn part1(&self, input: &Parsed) -> Result<Answer> {
+        Solver::part1(self, downcast::<S>(input))
+    }
+
+    fn part2(&self, input: &Parsed) -> Result<Answer> {
+        Solver::part2(self, downcast::<S>(input))
+    }
+}
+
+fn downcast<S: Solver>(input: &Parsed) -> &S::Input {
+    input.0.downcast_ref().expect("Input parsed by another day's solver")
+}
+
+/// The solver for each day solved so far, in order
+pub static SOLVERS: [(u8, &dyn Puzzle); 12] = [
+    (1, &day01::Day01),
+    (2, &day02::Day02),
+    (3, &day03::Day03),
+    (4, &day04::Day04),
+    (5, &day05::Day05),
+    (7, &day07::Day07),
+    (9, &day09::Day09),
+    (11, &day11::Day11),
+    (13, &day13::Day13),
+    (17, &day17::Day17),
+    (21, &day21::Day21),
+    (23, &day23::Day23),
+];
+
+/// The solver for a day, if it's been solved
+pub fn solver(day: u8) -> Option<&'static dyn Puzzle> {
+    SOLVERS.iter().find(|(d, _)| *d == day).map(|(_, solver)| *solver)
+}
+
+#[test]
+fn registry() -> Result<()> {
+    assert!(SOLVERS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    assert!(solver(6).is_none());
+
+    let day01 = solver(1).unwrap();
+    assert_eq!(day01.solve("12\n14\n1969\n100756\n")?, [Answer::Number(34241), Answer::Number(51316)]);
+    assert_eq!(Answer::from("RJLFBUCU").to_string(), "RJLFBUCU");
+    assert_eq!(Answer::from(usize::MAX), Answer::Text(usize::MAX.to_string()));
+    assert_eq!(Answer::from(u32::MAX), Answer::Number(u32::MAX.into()));
+
+    Ok(())
+}
@@ -0,0 +1,20 @@
+use advent_of_code_2019::answers::{self, Answers};
+use advent_of_code_2019::input::{self, Inputs};
+use advent_of_code_2019::solver::SOLVERS;
+use advent_of_code_2019::Result;
+
+/// Every solved day still gets its known answers from its real puzzle input
+#[test]
+fn known_answers() -> Result<()> {
+    let answers = Answers::load(answers::DEFAULT_PATH)?;
+    // The checked-in inputs, whatever AOC_INPUT_DIR says
+    let inputs = Inputs::in_dir(input::DEFAULT_DIR);
+
+    for (day, solver) in SOLVERS.iter() {
+        let [part1, part2] = solver.solve(&inputs.read(*day, &inputs.source(*day, 1))?)?;
+        assert_eq!(answers.get(*day, 1), Some(&part1), "Day {}, Part 1", day);
+        assert_eq!(answers.get(*day, 2), Some(&part2), "Day {}, Part 2", day);
+    }
+
+    Ok(())
+}